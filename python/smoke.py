@@ -0,0 +1,46 @@
+"""Smoke test for the Python bindings.
+
+Build and install the module into the current environment first:
+
+    maturin develop --release
+
+then run `python python/smoke.py`. It plays a game by always taking the
+placement that leaves the fewest holes, checking the board arrays as it goes.
+"""
+
+import numpy as np
+
+import tetris
+
+
+def holes(board):
+    filled = board != 0
+    # A cell is covered once any cell above it in its column is filled.
+    covered = np.logical_or.accumulate(filled, axis=0)
+    return int(np.count_nonzero(covered & ~filled))
+
+
+def main():
+    game = tetris.Game(mode="marathon", seed=7, randomizer="bag")
+    board = game.render_board()
+    assert board.shape == (tetris.HEIGHT, tetris.WIDTH), board.shape
+    assert board.dtype == np.uint8, board.dtype
+    assert len(tetris.CELLS) > int(board.max())
+    assert game.active_piece is not None
+
+    while not game.is_over and game.pieces < 100:
+        placements = game.placements()
+        assert placements, "a fresh piece always has somewhere to go"
+        assert all(p.path[-1] == "hard_drop" for p in placements)
+        best = min(placements, key=lambda p: holes(p.apply(game)))
+        game.place(best)
+        assert holes(game.board()) == game.holes()
+
+    heights = game.heights()
+    assert len(heights) == tetris.WIDTH
+    assert max(heights) <= tetris.HEIGHT
+    print(f"{game!r}, heights {heights}")
+
+
+if __name__ == "__main__":
+    main()
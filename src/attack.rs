@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+// Garbage sent for each extra clear in a row, indexed by the combo count: the
+// first clear of a chain is combo 0.
+const COMBO_ATTACK: [u32; 12] = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+const PERFECT_CLEAR_ATTACK: u32 = 10;
+
+// What a single piece placement cleared.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Clear {
+    pub lines: u32,
+    pub t_spin: bool,
+    pub perfect_clear: bool,
+}
+
+impl Clear {
+    // Tetrises and T-spin clears keep a back-to-back chain going.
+    pub fn is_difficult(&self) -> bool {
+        self.lines == 4 || (self.t_spin && self.lines > 0)
+    }
+}
+
+// Lines of garbage sent by a clear under the guideline attack table. `combo`
+// is how many clears came directly before this one, and `back_to_back` whether
+// the previous clear was also a difficult one.
+pub fn attack(clear: &Clear, combo: u32, back_to_back: bool) -> u32 {
+    if clear.lines == 0 {
+        return 0;
+    }
+    let base = match (clear.t_spin, clear.lines) {
+        (true, lines) => 2 * lines,
+        (false, 4) => 4,
+        (false, lines) => lines - 1,
+    };
+    let back_to_back_bonus = u32::from(back_to_back && clear.is_difficult());
+    let combo_bonus = COMBO_ATTACK[(combo as usize).min(COMBO_ATTACK.len() - 1)];
+    let perfect_clear_bonus = if clear.perfect_clear { PERFECT_CLEAR_ATTACK } else { 0 };
+    base + back_to_back_bonus + combo_bonus + perfect_clear_bonus
+}
@@ -0,0 +1,417 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::attack::{self, Clear};
+use crate::game::{Board, Cell, Command, Game, HEIGHT, WIDTH};
+use crate::mode::FRAMES_PER_SECOND;
+use crate::movegen::{self, Placement};
+use crate::tetromino::{PositionedTetromino, Tetromino};
+
+pub const WEIGHT_COUNT: usize = 9;
+
+// How much each feature of a board counts towards its score. Features that
+// make a board worse get negative weights.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Weights {
+    pub aggregate_height: f64,
+    pub holes: f64,
+    pub bumpiness: f64,
+    pub wells: f64,
+    pub row_transitions: f64,
+    pub column_transitions: f64,
+    pub t_slots: f64,
+    pub lines: f64,
+    pub attack: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            aggregate_height: -0.5,
+            holes: -4.0,
+            bumpiness: -0.2,
+            wells: -0.3,
+            row_transitions: -0.4,
+            column_transitions: -0.9,
+            t_slots: 1.0,
+            lines: 0.5,
+            attack: 1.5,
+        }
+    }
+}
+
+impl Weights {
+    pub fn to_array(&self) -> [f64; WEIGHT_COUNT] {
+        [
+            self.aggregate_height,
+            self.holes,
+            self.bumpiness,
+            self.wells,
+            self.row_transitions,
+            self.column_transitions,
+            self.t_slots,
+            self.lines,
+            self.attack,
+        ]
+    }
+
+    pub fn from_array(values: [f64; WEIGHT_COUNT]) -> Self {
+        let [
+            aggregate_height,
+            holes,
+            bumpiness,
+            wells,
+            row_transitions,
+            column_transitions,
+            t_slots,
+            lines,
+            attack,
+        ] = values;
+        Self {
+            aggregate_height,
+            holes,
+            bumpiness,
+            wells,
+            row_transitions,
+            column_transitions,
+            t_slots,
+            lines,
+            attack,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+// Picks where each piece should go.
+pub trait Planner {
+    // Chooses one of the placements the game's active piece can reach, or
+    // None to just drop it where it is.
+    fn plan(&mut self, game: &Game, placements: &[Placement]) -> io::Result<Option<Placement>>;
+}
+
+impl<P: Planner + ?Sized> Planner for &mut P {
+    fn plan(&mut self, game: &Game, placements: &[Placement]) -> io::Result<Option<Placement>> {
+        (**self).plan(game, placements)
+    }
+}
+
+impl<P: Planner + ?Sized> Planner for Box<P> {
+    fn plan(&mut self, game: &Game, placements: &[Placement]) -> io::Result<Option<Placement>> {
+        (**self).plan(game, placements)
+    }
+}
+
+// Steers the active piece to wherever the planner wants it, one command per
+// frame, just like a player at the keyboard.
+#[derive(Clone, Debug)]
+pub struct Bot<P = Heuristic> {
+    planner: P,
+    // Where the current piece is headed, and the piece count when it was
+    // picked, so a new piece gets a new target.
+    target: Option<Placement>,
+    planned_at: u32,
+    // The rest of the way there, and where the piece should be if it's still
+    // on course.
+    path: VecDeque<Command>,
+    expected: Option<PositionedTetromino>,
+    // The fewest frames each piece may take, and the frame the current one
+    // was first seen on.
+    frames_per_piece: u64,
+    piece_started: u64,
+}
+
+// How strong a bot opponent is: the most pieces it places a second, and how
+// many pieces from the next queue it plans around.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Difficulty {
+    pub pps: f64,
+    pub depth: usize,
+}
+
+impl Difficulty {
+    pub const NAMES: [&'static str; 4] = ["easy", "medium", "hard", "expert"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (pps, depth) = match name {
+            "easy" => (0.5, 0),
+            "medium" => (1.0, 0),
+            "hard" => (2.0, 1),
+            "expert" => (f64::INFINITY, 1),
+            _ => return None,
+        };
+        Some(Self { pps, depth })
+    }
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self::from_name("medium").expect("medium is a difficulty")
+    }
+}
+
+impl Default for Bot {
+    fn default() -> Self {
+        Self::new(Heuristic::default())
+    }
+}
+
+impl<P: Planner> Bot<P> {
+    pub fn new(planner: P) -> Self {
+        Self {
+            planner,
+            target: None,
+            planned_at: 0,
+            path: VecDeque::new(),
+            expected: None,
+            frames_per_piece: 0,
+            piece_started: 0,
+        }
+    }
+
+    // Holds the bot back to at most `pps` pieces a second, by having it wait
+    // before dropping each piece.
+    pub fn with_pps_cap(mut self, pps: f64) -> Self {
+        self.frames_per_piece = (FRAMES_PER_SECOND as f64 / pps).ceil() as u64;
+        self
+    }
+
+    pub fn planner(&self) -> &P {
+        &self.planner
+    }
+
+    // The command to feed the game this frame.
+    pub fn next_command(&mut self, game: &Game) -> io::Result<Command> {
+        let Some(active_piece) = game.active_piece() else {
+            return Ok(Command::NoOp);
+        };
+        if game.is_over() {
+            return Ok(Command::NoOp);
+        }
+        let board = game.board();
+
+        // Gravity can pull the piece off the planned path, in which case the
+        // way to the target is worked out again from where it is, and a new
+        // target picked if it can't be reached any more.
+        let new_piece = self.planned_at != game.render_pieces();
+        if new_piece || self.expected.as_ref() != Some(active_piece) {
+            let placements = movegen::placements_from(active_piece, board);
+            let reaches = |placement: &Placement, target: &Placement| {
+                placement.piece == target.piece && placement.t_spin == target.t_spin
+            };
+            let reachable = self.target.as_ref().and_then(|target| {
+                placements.iter().find(|placement| reaches(placement, target))
+            });
+            self.target = match reachable {
+                Some(placement) if !new_piece => Some(placement.clone()),
+                _ => self.planner.plan(game, &placements)?,
+            };
+            self.planned_at = game.render_pieces();
+            self.path = self.target.as_ref()
+                .map(|target| target.path.iter().copied().collect())
+                .unwrap_or_default();
+            if new_piece {
+                self.piece_started = game.frame();
+            }
+        }
+        // The piece is moved into place straight away, so gravity can't lock
+        // it anywhere else first, then held there until it's been out long
+        // enough to drop.
+        let command = self.path.front().copied().unwrap_or(Command::HardDrop);
+        let drop_at = self.piece_started.saturating_add(self.frames_per_piece);
+        if command == Command::HardDrop && game.frame() + 1 < drop_at {
+            self.expected = Some(active_piece.clone());
+            return Ok(Command::NoOp);
+        }
+        self.path.pop_front();
+        self.expected = Some(movegen::apply(active_piece, command, board));
+        Ok(command)
+    }
+}
+
+// Scores every placement with a weighted sum of board features and goes for
+// the best.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Heuristic {
+    weights: Weights,
+    // How many pieces from the next queue to plan around as well.
+    depth: usize,
+}
+
+impl Planner for Heuristic {
+    fn plan(&mut self, game: &Game, placements: &[Placement]) -> io::Result<Option<Placement>> {
+        let queue = [game.next_piece().clone()];
+        let queue = &queue[..self.depth.min(queue.len())];
+        Ok(self.search(game.board(), placements.to_vec(), queue)
+            .map(|(_, placement)| placement))
+    }
+}
+
+impl Heuristic {
+    pub fn new(weights: Weights, depth: usize) -> Self {
+        Self { weights, depth }
+    }
+
+    pub fn weights(&self) -> &Weights {
+        &self.weights
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    // The best placement for a piece, looking at how each of the pieces in
+    // `queue` could follow it.
+    pub fn best_placement(
+        &self,
+        board: &Board,
+        piece: &PositionedTetromino,
+        queue: &[Tetromino],
+    ) -> Option<Placement> {
+        let placements = movegen::placements_from(piece, board);
+        self.search(board, placements, queue).map(|(_, placement)| placement)
+    }
+
+    // Scores each placement by what it clears plus the best that can be done
+    // with the rest of the queue afterwards, down to how the final board
+    // looks. Placements that leave no room for the next piece to spawn score
+    // negative infinity.
+    fn search(
+        &self,
+        board: &Board,
+        placements: Vec<Placement>,
+        queue: &[Tetromino],
+    ) -> Option<(f64, Placement)> {
+        placements.into_iter()
+            .map(|placement| {
+                let (after, clear) = lock(board, &placement);
+                let reward = self.weights.lines * clear.lines as f64
+                    + self.weights.attack * attack::attack(&clear, 0, false) as f64;
+                let future = match queue.split_first() {
+                    Some((next, rest)) => {
+                        let next_placements = movegen::placements(&after, next);
+                        self.search(&after, next_placements, rest)
+                            .map_or(f64::NEG_INFINITY, |(score, _)| score)
+                    }
+                    None => self.evaluate(&after),
+                };
+                (reward + future, placement)
+            })
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+    }
+
+    pub fn evaluate(&self, board: &Board) -> f64 {
+        let features = Features::of(board);
+        let weights = &self.weights;
+        weights.aggregate_height * features.aggregate_height as f64
+            + weights.holes * features.holes as f64
+            + weights.bumpiness * features.bumpiness as f64
+            + weights.wells * features.wells as f64
+            + weights.row_transitions * features.row_transitions as f64
+            + weights.column_transitions * features.column_transitions as f64
+            + weights.t_slots * features.t_slots as f64
+    }
+}
+
+// Locks a placement into the board and clears any full rows, the way the
+// game would.
+pub fn lock(board: &Board, placement: &Placement) -> (Board, Clear) {
+    let (after, lines) = clear_full_rows(&placement.apply(board));
+    let clear = Clear {
+        lines,
+        t_spin: placement.t_spin,
+        perfect_clear: lines > 0
+            && after.iter().all(|row| row.iter().all(|cell| *cell == Cell::Empty)),
+    };
+    (after, clear)
+}
+
+// The board with its full rows taken out, and how many there were.
+pub fn clear_full_rows(board: &Board) -> (Board, u32) {
+    let mut after = [[Cell::Empty; WIDTH as usize]; HEIGHT as usize];
+    let mut y = HEIGHT as usize;
+    for row in board.iter().rev().filter(|row| row.contains(&Cell::Empty)) {
+        y -= 1;
+        after[y] = *row;
+    }
+    (after, y as u32)
+}
+
+// The things about a board the bot weighs up.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Features {
+    // Sum of the column heights.
+    pub aggregate_height: u32,
+    // Empty cells with a filled cell somewhere above them.
+    pub holes: u32,
+    // Sum of the height differences between neighbouring columns.
+    pub bumpiness: u32,
+    // Sum of how far each column sits below both of its neighbours, with the
+    // walls counting as infinitely high.
+    pub wells: u32,
+    // Changes between filled and empty going along each row, and down each
+    // column. The walls and floor count as filled.
+    pub row_transitions: u32,
+    pub column_transitions: u32,
+    // Slots a T piece could be spun down into.
+    pub t_slots: u32,
+}
+
+impl Features {
+    pub fn of(board: &Board) -> Self {
+        let filled = |y: i32, x: i32| {
+            !(0..HEIGHT).contains(&y) || !(0..WIDTH).contains(&x)
+                || board[y as usize][x as usize] != Cell::Empty
+        };
+        let heights: Vec<i32> = (0..WIDTH)
+            .map(|x| (0..HEIGHT).find(|&y| filled(y, x)).map_or(0, |y| HEIGHT - y))
+            .collect();
+        let mut features = Features {
+            aggregate_height: heights.iter().sum::<i32>() as u32,
+            bumpiness: heights.windows(2).map(|pair| pair[0].abs_diff(pair[1])).sum(),
+            ..Default::default()
+        };
+        for x in 0..WIDTH {
+            let left = if x > 0 { heights[x as usize - 1] } else { HEIGHT };
+            let right = if x < WIDTH - 1 { heights[x as usize + 1] } else { HEIGHT };
+            features.wells += (left.min(right) - heights[x as usize]).max(0) as u32;
+            features.holes += (HEIGHT - heights[x as usize]..HEIGHT)
+                .filter(|&y| !filled(y, x))
+                .count() as u32;
+            features.column_transitions += (0..HEIGHT)
+                .filter(|&y| filled(y, x) != filled(y + 1, x))
+                .count() as u32;
+        }
+        for y in 0..HEIGHT {
+            features.row_transitions += (-1..WIDTH)
+                .filter(|&x| filled(y, x) != filled(y, x + 1))
+                .count() as u32;
+        }
+        // A T-shaped gap pointing down, with both bottom corners filled and a
+        // roof over at least one of the top ones.
+        for y in 1..HEIGHT - 1 {
+            for x in 1..WIDTH - 1 {
+                let gap = !filled(y, x - 1) && !filled(y, x) && !filled(y, x + 1)
+                    && !filled(y + 1, x) && !filled(y - 1, x);
+                if gap && filled(y + 1, x - 1) && filled(y + 1, x + 1)
+                    && (filled(y - 1, x - 1) || filled(y - 1, x + 1)) {
+                    features.t_slots += 1;
+                }
+            }
+        }
+        features
+    }
+}
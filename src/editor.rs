@@ -0,0 +1,416 @@
+// A screen for painting a board and picking its pieces, to play from.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crossterm::event;
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::{Frame, Terminal};
+use ratatui::backend::Backend;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, BorderType, Paragraph};
+use serde::{Deserialize, Serialize};
+
+use crate::{render_cell, SIDE_BAR_WIDTH};
+use crate::fumen::{self, Page, URL_PREFIX};
+use crate::game::{Cell, HEIGHT, Setup, WIDTH};
+use crate::storage;
+use crate::tetromino::Tetromino;
+
+// Bump this whenever `Setup` changes shape, so old boards are rejected rather
+// than misread.
+pub const BOARD_VERSION: u32 = 1;
+
+// The longest name a board can be saved under.
+const MAX_FILE_NAME_LEN: usize = 32;
+
+// The keys that pick a brush, along with what it paints.
+const BRUSHES: [(char, Cell); 9] = [
+    ('i', Cell::IBlock),
+    ('j', Cell::JBlock),
+    ('l', Cell::LBlock),
+    ('o', Cell::OBlock),
+    ('s', Cell::SBlock),
+    ('t', Cell::TBlock),
+    ('z', Cell::ZBlock),
+    ('g', Cell::Garbage),
+    ('x', Cell::Empty),
+];
+
+const PIECES: [Tetromino; 7] = [
+    Tetromino::I(0),
+    Tetromino::J(0),
+    Tetromino::L(0),
+    Tetromino::O,
+    Tetromino::S(0),
+    Tetromino::T(0),
+    Tetromino::Z(0),
+];
+
+const HELP: [&str; 9] = [
+    "Arrows  move",
+    "Space   paint",
+    "Bksp    erase",
+    "ijlostz g x brush",
+    "Tab     queue/hold",
+    "^S/^O   save/load",
+    "^F/^E   fumen in/out",
+    "^N      clear all",
+    "Enter   play",
+];
+
+pub enum EditorChoice {
+    Play,
+    Quit,
+}
+
+// A board saved from the editor, to be loaded back into it later.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedBoard {
+    version: u32,
+    pub setup: Setup,
+}
+
+impl SavedBoard {
+    pub fn new(setup: Setup) -> Self {
+        Self {
+            version: BOARD_VERSION,
+            setup,
+        }
+    }
+
+    // Boards are kept by name, so they can be loaded again without typing a
+    // whole path.
+    pub fn path_for(name: &str) -> PathBuf {
+        storage::data_dir().join("boards").join(format!("{name}.json"))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let saved: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if saved.version != BOARD_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported board version {}", saved.version),
+            ));
+        }
+        Ok(saved)
+    }
+}
+
+// Which part of the setup typed letters go to.
+#[derive(Clone, Copy, PartialEq)]
+enum Focus {
+    Board,
+    Queue,
+    Hold,
+}
+
+// A line of text being typed in at the bottom of the screen.
+#[derive(Clone, Copy, PartialEq)]
+enum Prompt {
+    Save,
+    Load,
+    Fumen,
+}
+
+impl Prompt {
+    fn label(&self) -> &'static str {
+        match self {
+            Prompt::Save => "Save as",
+            Prompt::Load => "Load",
+            Prompt::Fumen => "Fumen",
+        }
+    }
+
+    fn accepts(&self, c: char) -> bool {
+        match self {
+            Prompt::Save | Prompt::Load => c.is_ascii_alphanumeric() || c == '-' || c == '_',
+            Prompt::Fumen => c.is_ascii_graphic(),
+        }
+    }
+}
+
+struct Editor<'a> {
+    setup: &'a mut Setup,
+    // (row, column) on the board.
+    cursor: (usize, usize),
+    brush: Cell,
+    focus: Focus,
+    prompt: Option<(Prompt, String)>,
+    status: String,
+}
+
+impl Editor<'_> {
+    fn paint(&mut self, row: usize, column: usize, cell: Cell) {
+        self.cursor = (row, column);
+        self.setup.board[row][column] = cell;
+    }
+
+    fn move_cursor(&mut self, rows: isize, columns: isize) {
+        let (row, column) = self.cursor;
+        self.cursor = (
+            row.saturating_add_signed(rows).min(HEIGHT as usize - 1),
+            column.saturating_add_signed(columns).min(WIDTH as usize - 1),
+        );
+    }
+
+    // The current piece comes first, followed by the rest of the queue.
+    fn push_piece(&mut self, piece: Tetromino) {
+        match self.setup.current {
+            None => self.setup.current = Some(piece),
+            Some(_) => self.setup.queue.push(piece),
+        }
+    }
+
+    fn pop_piece(&mut self) {
+        if self.setup.queue.pop().is_none() {
+            self.setup.current = None;
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, messages: &mut Vec<String>) -> Option<EditorChoice> {
+        if let Some((prompt, mut text)) = self.prompt.take() {
+            match key.code {
+                KeyCode::Esc => (),
+                KeyCode::Enter => self.finish_prompt(prompt, &text),
+                KeyCode::Backspace => {
+                    text.pop();
+                    self.prompt = Some((prompt, text));
+                }
+                KeyCode::Char(c) if prompt.accepts(c)
+                    && (prompt == Prompt::Fumen || text.len() < MAX_FILE_NAME_LEN) => {
+                    text.push(c);
+                    self.prompt = Some((prompt, text));
+                }
+                _ => self.prompt = Some((prompt, text)),
+            }
+            return None;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            match key.code {
+                KeyCode::Char('s') => self.prompt = Some((Prompt::Save, String::new())),
+                KeyCode::Char('o') => self.prompt = Some((Prompt::Load, String::new())),
+                KeyCode::Char('f') => self.prompt = Some((Prompt::Fumen, String::new())),
+                KeyCode::Char('e') => {
+                    messages.push(format!("{URL_PREFIX}{}", fumen::encode(&[Page::from_setup(self.setup)])));
+                    self.status = String::from("Fumen URL will be printed on exit");
+                }
+                KeyCode::Char('n') => {
+                    *self.setup = Setup::default();
+                    self.status = String::from("Cleared");
+                }
+                _ => (),
+            }
+            return None;
+        }
+        match key.code {
+            KeyCode::Esc => return Some(EditorChoice::Quit),
+            KeyCode::Enter => return Some(EditorChoice::Play),
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Board => Focus::Queue,
+                    Focus::Queue => Focus::Hold,
+                    Focus::Hold => Focus::Board,
+                };
+            }
+            KeyCode::Up => self.move_cursor(-1, 0),
+            KeyCode::Down => self.move_cursor(1, 0),
+            KeyCode::Left => self.move_cursor(0, -1),
+            KeyCode::Right => self.move_cursor(0, 1),
+            KeyCode::Char(' ') => {
+                let (row, column) = self.cursor;
+                self.paint(row, column, self.brush);
+            }
+            KeyCode::Backspace | KeyCode::Delete => match self.focus {
+                Focus::Board => {
+                    let (row, column) = self.cursor;
+                    self.paint(row, column, Cell::Empty);
+                }
+                Focus::Queue => self.pop_piece(),
+                Focus::Hold => self.setup.hold = None,
+            },
+            KeyCode::Char(c) => {
+                let c = c.to_ascii_lowercase();
+                match self.focus {
+                    Focus::Board => {
+                        if let Some(&(_, cell)) = BRUSHES.iter().find(|(key, _)| *key == c) {
+                            self.brush = cell;
+                        }
+                    }
+                    Focus::Queue => {
+                        if let Some(piece) = piece_of_letter(c) {
+                            self.push_piece(piece);
+                        }
+                    }
+                    Focus::Hold => {
+                        if let Some(piece) = piece_of_letter(c) {
+                            self.setup.hold = Some(piece);
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+        None
+    }
+
+    fn finish_prompt(&mut self, prompt: Prompt, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let result = match prompt {
+            Prompt::Save => {
+                let path = SavedBoard::path_for(text);
+                SavedBoard::new(self.setup.clone()).save(&path)
+                    .map(|()| format!("Saved to {}", path.display()))
+            }
+            Prompt::Load => SavedBoard::load(&SavedBoard::path_for(text)).map(|saved| {
+                *self.setup = saved.setup;
+                format!("Loaded {text}")
+            }),
+            Prompt::Fumen => fumen::import(text, 1).map(|setup| {
+                *self.setup = setup;
+                String::from("Imported the first fumen page")
+            }),
+        };
+        self.status = result.unwrap_or_else(|err| format!("Error: {err}"));
+    }
+
+    // Left button paints with the brush, right button erases.
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let cell = match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) | MouseEventKind::Drag(MouseButton::Left) => self.brush,
+            MouseEventKind::Down(MouseButton::Right) | MouseEventKind::Drag(MouseButton::Right) => Cell::Empty,
+            _ => return,
+        };
+        if let Some((row, column)) = cell_at(mouse.column, mouse.row) {
+            self.paint(row, column, cell);
+        }
+    }
+}
+
+// Where the board is drawn, so mouse positions can be mapped back onto it.
+fn board_area() -> Rect {
+    Rect::new(0, 0, WIDTH as u16 * 2 + 2, HEIGHT as u16 + 2)
+}
+
+// The (row, column) of the board cell at a screen position, each cell being
+// two characters wide.
+fn cell_at(x: u16, y: u16) -> Option<(usize, usize)> {
+    let area = board_area();
+    let column = x.checked_sub(area.x + 1)? / 2;
+    let row = y.checked_sub(area.y + 1)?;
+    (row < HEIGHT as u16 && column < WIDTH as u16).then_some((row as usize, column as usize))
+}
+
+fn piece_of_letter(letter: char) -> Option<Tetromino> {
+    PIECES.into_iter().find(|piece| {
+        BRUSHES.iter().any(|&(key, cell)| key == letter && cell == piece.get_cell_type())
+    })
+}
+
+fn letter_of(piece: &Tetromino) -> char {
+    BRUSHES.iter()
+        .find(|(_, cell)| *cell == piece.get_cell_type())
+        .map_or('?', |(key, _)| key.to_ascii_uppercase())
+}
+
+fn piece_span(piece: &Tetromino) -> Span<'static> {
+    Span::styled(letter_of(piece).to_string(), render_cell(&piece.get_cell_type()).style)
+}
+
+fn render_editor<B: Backend>(f: &mut Frame<B>, editor: &Editor) {
+    let board: Vec<Line> = editor.setup.board.iter().enumerate().map(|(row, cells)| {
+        Line::from(cells.iter().enumerate().map(|(column, cell)| {
+            if (row, column) == editor.cursor && editor.focus == Focus::Board {
+                let style = render_cell(&editor.brush).style.add_modifier(Modifier::REVERSED);
+                Span::styled("[]", style)
+            } else {
+                render_cell(cell)
+            }
+        }).collect::<Vec<Span>>())
+    }).collect();
+    f.render_widget(
+        Paragraph::new(board).block(Block::default()
+            .title("Editor")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double)),
+        board_area(),
+    );
+
+    let label = |name: &'static str, focus: Focus| {
+        let style = if editor.focus == focus {
+            Style::new().add_modifier(Modifier::BOLD | Modifier::REVERSED)
+        } else {
+            Style::new()
+        };
+        Span::styled(name, style)
+    };
+    let mut queue = vec![label("Queue:", Focus::Queue), Span::raw(" ")];
+    queue.extend(editor.setup.current.iter().chain(&editor.setup.queue).map(piece_span));
+    let mut hold = vec![label("Hold:", Focus::Hold), Span::raw(" ")];
+    hold.extend(editor.setup.hold.iter().map(piece_span));
+    let mut lines = vec![
+        Line::from(vec![Span::raw("Brush: "), render_cell(&editor.brush)]),
+        Line::from(queue),
+        Line::from(hold),
+        Line::from(""),
+    ];
+    lines.extend(HELP.iter().map(|&help| Line::from(help)));
+    let board = board_area();
+    let side = Rect::new(board.right(), board.y, SIDE_BAR_WIDTH + 4, board.height)
+        .intersection(f.size());
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL)),
+        side,
+    );
+
+    let status = match &editor.prompt {
+        Some((prompt, text)) => format!("{}: {text}_", prompt.label()),
+        None => editor.status.clone(),
+    };
+    let status_area = Rect::new(0, board.bottom(), f.size().width, 1).intersection(f.size());
+    f.render_widget(Paragraph::new(status), status_area);
+}
+
+// Lets the player paint a board and pick the queue and hold, with the
+// keyboard or the mouse. The setup is edited in place, so it's kept between
+// visits; playing starts a game from it.
+pub fn run_editor<B: Backend>(
+    terminal: &mut Terminal<B>,
+    setup: &mut Setup,
+    messages: &mut Vec<String>,
+) -> io::Result<EditorChoice> {
+    let mut editor = Editor {
+        setup,
+        cursor: (HEIGHT as usize - 1, 0),
+        brush: Cell::Garbage,
+        focus: Focus::Board,
+        prompt: None,
+        status: String::new(),
+    };
+    loop {
+        terminal.draw(|f| render_editor(f, &editor))?;
+        match event::read()? {
+            Event::Key(key) => {
+                if let Some(choice) = editor.handle_key(key, messages) {
+                    return Ok(choice);
+                }
+            }
+            Event::Mouse(mouse) if editor.prompt.is_none() => editor.handle_mouse(mouse),
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,331 @@
+// A gym-style environment for training agents against the engine: reset it
+// with a seed, then step it with actions until it reports the game is done.
+
+use std::io;
+
+use crate::bot::{Bot, Heuristic, Planner};
+use crate::game::{Cell, Command, Game, HEIGHT, WIDTH};
+use crate::mode::Mode;
+use crate::movegen::{self, Placement};
+use crate::randomizer::Randomizer;
+use crate::tetromino::{PositionedTetromino, Tetromino};
+
+const PIECE_KINDS: usize = 7;
+const GRID_SIZE: usize = (WIDTH * HEIGHT) as usize;
+// The active piece and the one after it.
+const QUEUE_LEN: usize = 2;
+
+pub const OBSERVATION_SIZE: usize = 2 * GRID_SIZE + (QUEUE_LEN + 1) * PIECE_KINDS;
+
+// What an action means: a single command for the next frame, or which of
+// `Env::placements` to put the active piece in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ActionSpace {
+    Commands,
+    Placements,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Command(Command),
+    Placement(usize),
+}
+
+// Everything is flattened into floats, one for each cell or piece kind,
+// with grids running row by row from the top left.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation {
+    // 1.0 where the stack has a block.
+    pub grid: Vec<f32>,
+    // 1.0 where the active piece is.
+    pub piece: Vec<f32>,
+    // One-hot piece kinds for the active piece and the next one.
+    pub queue: Vec<f32>,
+    // One-hot kind of the held piece. There's no hold in this game, so it's
+    // always empty, but it's kept so agents can be moved between engines.
+    pub hold: Vec<f32>,
+}
+
+impl Observation {
+    pub fn of(game: &Game) -> Self {
+        let mut grid = vec![0.0; GRID_SIZE];
+        for (cell, filled) in game.board().iter().flatten().zip(&mut grid) {
+            if *cell != Cell::Empty {
+                *filled = 1.0;
+            }
+        }
+        let mut piece = vec![0.0; GRID_SIZE];
+        let mut queue = vec![0.0; QUEUE_LEN * PIECE_KINDS];
+        if let Some(active_piece) = game.active_piece() {
+            for (y, x) in active_piece.get_coords() {
+                piece[(y * WIDTH + x) as usize] = 1.0;
+            }
+            queue[piece_index(active_piece.tetromino())] = 1.0;
+        }
+        queue[PIECE_KINDS + piece_index(game.next_piece())] = 1.0;
+        Self { grid, piece, queue, hold: vec![0.0; PIECE_KINDS] }
+    }
+
+    // All of it in one vector of OBSERVATION_SIZE floats.
+    pub fn to_vec(&self) -> Vec<f32> {
+        [&self.grid, &self.piece, &self.queue, &self.hold].into_iter()
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
+
+fn piece_index(tetromino: &Tetromino) -> usize {
+    match tetromino {
+        Tetromino::I(_) => 0,
+        Tetromino::J(_) => 1,
+        Tetromino::L(_) => 2,
+        Tetromino::O => 3,
+        Tetromino::S(_) => 4,
+        Tetromino::T(_) => 5,
+        Tetromino::Z(_) => 6,
+    }
+}
+
+// Extra detail about a step, beyond the reward.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Info {
+    pub score: i32,
+    pub lines: u32,
+    pub pieces: u32,
+    pub frame: u64,
+    // Lines cleared during this step.
+    pub cleared: u32,
+    // How many placements the next piece has to choose from.
+    pub placements: usize,
+    // Where a placement action's piece actually locked. Gravity or rising
+    // garbage can leave the chosen placement out of reach, in which case the
+    // piece is dropped from wherever it got to instead.
+    pub placed: Option<PositionedTetromino>,
+}
+
+impl Info {
+    fn of(game: &Game, lines_before: u32, placements: usize, placed: Option<PositionedTetromino>) -> Self {
+        Self {
+            score: game.render_score(),
+            lines: game.render_lines(),
+            pieces: game.render_pieces(),
+            frame: game.frame(),
+            cleared: game.render_lines().saturating_sub(lines_before),
+            placements,
+            placed,
+        }
+    }
+}
+
+// The parts of a game's state that rewards are usually worked out from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Progress {
+    pub score: i32,
+    pub lines: u32,
+    pub pieces: u32,
+    pub attack: u32,
+    pub over: bool,
+}
+
+impl Progress {
+    pub fn of(game: &Game) -> Self {
+        Self {
+            score: game.render_score(),
+            lines: game.render_lines(),
+            pieces: game.render_pieces(),
+            attack: game.stats().attack,
+            over: game.is_over(),
+        }
+    }
+}
+
+// Works out the reward for a step from how the game moved on, with `game`
+// there for anything more involved.
+pub trait Reward {
+    fn reward(&mut self, before: &Progress, after: &Progress, game: &Game) -> f64;
+
+    // Called with the new game whenever the environment starts one, for
+    // rewards that keep track of anything between steps.
+    fn reset(&mut self, _game: &Game) {}
+}
+
+impl<F: FnMut(&Progress, &Progress, &Game) -> f64> Reward for F {
+    fn reward(&mut self, before: &Progress, after: &Progress, game: &Game) -> f64 {
+        self(before, after, game)
+    }
+}
+
+// Points scored.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScoreDelta;
+
+impl Reward for ScoreDelta {
+    fn reward(&mut self, before: &Progress, after: &Progress, _game: &Game) -> f64 {
+        (after.score - before.score) as f64
+    }
+}
+
+// Lines cleared, plus a bonus for each piece placed and a penalty for
+// topping out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Survival {
+    pub per_piece: f64,
+    pub game_over: f64,
+}
+
+impl Default for Survival {
+    fn default() -> Self {
+        Self { per_piece: 0.01, game_over: -1.0 }
+    }
+}
+
+impl Reward for Survival {
+    fn reward(&mut self, before: &Progress, after: &Progress, _game: &Game) -> f64 {
+        let mut reward = after.lines as f64 - before.lines as f64
+            + self.per_piece * (after.pieces as f64 - before.pieces as f64);
+        if after.over && !before.over {
+            reward += self.game_over;
+        }
+        reward
+    }
+}
+
+// Lines cleared plus how much the bot's evaluation of the stack improved,
+// for agents that need a nudge before they ever clear a line.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Shaped {
+    pub heuristic: Heuristic,
+    last_evaluation: f64,
+}
+
+impl Shaped {
+    pub fn new(heuristic: Heuristic) -> Self {
+        Self { heuristic, last_evaluation: 0.0 }
+    }
+}
+
+impl Reward for Shaped {
+    fn reward(&mut self, before: &Progress, after: &Progress, game: &Game) -> f64 {
+        let evaluation = self.heuristic.evaluate(game.board());
+        let change = evaluation - self.last_evaluation;
+        self.last_evaluation = evaluation;
+        after.lines as f64 - before.lines as f64 + change
+    }
+
+    fn reset(&mut self, game: &Game) {
+        self.last_evaluation = self.heuristic.evaluate(game.board());
+    }
+}
+
+pub struct Env<R = ScoreDelta> {
+    mode: Mode,
+    randomizer: Randomizer,
+    space: ActionSpace,
+    reward: R,
+    game: Game,
+    placements: Vec<Placement>,
+}
+
+impl<R: Reward> Env<R> {
+    pub fn new(mode: Mode, randomizer: Randomizer, space: ActionSpace, reward: R) -> Self {
+        let game = Game::with_randomizer(mode.clone(), 0, randomizer);
+        let mut env = Self { mode, randomizer, space, reward, game, placements: Vec::new() };
+        env.reward.reset(&env.game);
+        env.update_placements();
+        env
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = Game::with_randomizer(self.mode.clone(), seed, self.randomizer);
+        self.reward.reset(&self.game);
+        self.update_placements();
+        Observation::of(&self.game)
+    }
+
+    // Applies an action. In the placements action space, a step places a
+    // whole piece and runs on until the next one is ready to move. Panics if
+    // the action doesn't belong to the environment's action space, or names a
+    // placement that isn't in `placements`.
+    pub fn step(&mut self, action: Action) -> (Observation, f64, bool, Info) {
+        let before = Progress::of(&self.game);
+        let placed = match (self.space, action) {
+            (ActionSpace::Commands, Action::Command(command)) => {
+                self.game.tick(command);
+                None
+            }
+            (ActionSpace::Placements, Action::Placement(index)) => {
+                let placement = self.placements.get(index).unwrap_or_else(|| panic!(
+                    "placement {index} out of range for {} placements", self.placements.len(),
+                ));
+                place(&mut self.game, placement.clone())
+            }
+            (space, action) => panic!("{action:?} isn't in the {space:?} action space"),
+        };
+        self.update_placements();
+        let after = Progress::of(&self.game);
+        let reward = self.reward.reward(&before, &after, &self.game);
+        let info = Info::of(&self.game, before.lines, self.placements.len(), placed);
+        (Observation::of(&self.game), reward, self.game.is_over(), info)
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn action_space(&self) -> ActionSpace {
+        self.space
+    }
+
+    // Where the active piece can go, in the order placement actions refer
+    // to them. Only kept up to date in the placements action space.
+    pub fn placements(&self) -> &[Placement] {
+        &self.placements
+    }
+
+    fn update_placements(&mut self) {
+        self.placements = match (self.space, self.game.active_piece()) {
+            (ActionSpace::Placements, Some(active_piece)) if !self.game.is_over() => {
+                movegen::placements_from(active_piece, self.game.board())
+            }
+            _ => Vec::new(),
+        };
+    }
+}
+
+// Always heads for the same placement, if it can still be reached.
+struct Fixed(Placement);
+
+impl Planner for Fixed {
+    fn plan(&mut self, _game: &Game, placements: &[Placement]) -> io::Result<Option<Placement>> {
+        Ok(placements.iter()
+            .find(|placement| placement.piece == self.0.piece && placement.t_spin == self.0.t_spin)
+            .cloned())
+    }
+}
+
+// Steers the active piece into the placement, then lets frames pass until
+// the next piece is ready to move. Returns where the piece locked, which
+// isn't the placement asked for if that stopped being reachable on the way.
+pub(crate) fn place(game: &mut Game, placement: Placement) -> Option<PositionedTetromino> {
+    let pieces = game.render_pieces();
+    let mut bot = Bot::new(Fixed(placement));
+    let mut placed = None;
+    while !game.is_over() && game.render_pieces() == pieces {
+        let command = bot.next_command(game).expect("a fixed placement can't fail");
+        // Whatever a frame does, a piece that locks on it ends up where the
+        // command takes it, dropped as far as it can go.
+        let landed = game.active_piece().map(|active_piece| {
+            let moved = movegen::apply(active_piece, command, game.board());
+            movegen::apply(&moved, Command::HardDrop, game.board())
+        });
+        game.tick(command);
+        if game.render_pieces() != pieces {
+            placed = landed;
+        }
+    }
+    while !game.is_over() && game.active_piece().is_none() {
+        game.tick(Command::NoOp);
+    }
+    placed
+}
@@ -0,0 +1,121 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::game::{Board, Command};
+use crate::movegen;
+use crate::tetromino::PositionedTetromino;
+
+// Only shifts and rotations count as finesse inputs. Soft drops are free,
+// since the piece could just as well be left to fall.
+pub fn is_finesse_input(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::Left | Command::Right | Command::Clockwise | Command::CounterClockwise
+    )
+}
+
+// Finds the cheapest way to reach every position the piece can get to from
+// `start`, as the sequence of commands to get there. Cheapest means the fewest
+// finesse inputs, with ties broken by the fewest soft drops.
+pub fn shortest_paths(
+    start: &PositionedTetromino,
+    board: &Board,
+) -> HashMap<PositionedTetromino, Vec<Command>> {
+    let mut paths = HashMap::new();
+    let mut nodes = vec![(start.clone(), Vec::new())];
+    let mut queue = BinaryHeap::from([Reverse((0, 0, 0))]);
+    while let Some(Reverse((inputs, drops, node))) = queue.pop() {
+        let (piece, path) = nodes[node].clone();
+        if paths.contains_key(&piece) {
+            continue;
+        }
+        for command in movegen::SEARCH_MOVES {
+            let moved = movegen::apply(&piece, command, board);
+            if moved == piece || paths.contains_key(&moved) {
+                continue;
+            }
+            let (inputs, drops) = if is_finesse_input(&command) {
+                (inputs + 1, drops)
+            } else {
+                (inputs, drops + 1)
+            };
+            let mut moved_path = path.clone();
+            moved_path.push(command);
+            nodes.push((moved, moved_path));
+            queue.push(Reverse((inputs, drops, nodes.len() - 1)));
+        }
+        paths.insert(piece, path);
+    }
+    paths
+}
+
+// The cheapest way to get the piece from `start` to a resting place at
+// `target`. Paths that end anywhere the piece can be hard dropped into the
+// target from count, so soft drops only show up where they're needed.
+pub fn optimal_inputs(
+    start: &PositionedTetromino,
+    target: &PositionedTetromino,
+    board: &Board,
+) -> Option<Vec<Command>> {
+    shortest_paths(start, board)
+        .into_iter()
+        .filter(|(piece, _)| {
+            let mut landed = piece.clone();
+            landed.drop_to_floor(board);
+            landed == *target
+        })
+        .map(|(_, path)| path)
+        .min_by_key(|path| (count_inputs(path), path.len()))
+}
+
+// Every position the piece can be hard dropped into without a soft drop
+// first, i.e. without tucks or spins.
+pub fn hard_drop_targets(start: &PositionedTetromino, board: &Board) -> Vec<PositionedTetromino> {
+    let mut targets: Vec<PositionedTetromino> = shortest_paths(start, board)
+        .into_iter()
+        .filter(|(_, path)| !path.contains(&Command::Drop))
+        .map(|(mut piece, _)| {
+            piece.drop_to_floor(board);
+            piece
+        })
+        .collect();
+    // Sort so the same board always gives the same targets, whatever order
+    // the hash map came out in.
+    targets.sort_by_key(|piece| {
+        let mut coords = piece.get_coords();
+        coords.sort();
+        coords
+    });
+    targets.dedup();
+    targets
+}
+
+pub fn count_inputs(commands: &[Command]) -> u32 {
+    commands.iter().filter(|command| is_finesse_input(command)).count() as u32
+}
+
+// Shows a key sequence as arrows. Soft drops in the middle of a sequence are
+// shown once per run, and trailing ones are left off since the piece would
+// be hard dropped from there. A sequence with nothing left is just "drop".
+pub fn format_inputs(commands: &[Command]) -> String {
+    let end = commands.iter()
+        .rposition(|command| *command != Command::Drop)
+        .map_or(0, |last| last + 1);
+    let mut text = String::new();
+    for (i, command) in commands[..end].iter().enumerate() {
+        let symbol = match command {
+            Command::Left => '←',
+            Command::Right => '→',
+            Command::Clockwise => '↻',
+            Command::CounterClockwise => '↺',
+            Command::Drop if i > 0 && commands[i - 1] == Command::Drop => continue,
+            Command::Drop => '↓',
+            _ => continue,
+        };
+        text.push(symbol);
+    }
+    if text.is_empty() {
+        text.push_str("drop");
+    }
+    text
+}
@@ -0,0 +1,643 @@
+// Fumen, the diagram format Tetris players share setups in. Only its current
+// version is read and written: "v115@" followed by base64 packing a field, a
+// piece and a comment for each page, with each field stored as the change
+// from the one before.
+//
+// Fumen fields are 23 rows tall with a row of rising garbage underneath.
+// Pages with blocks above this game's 20 rows can't be imported, and the
+// garbage row is left out. The hold slot and next pieces only appear in quiz
+// comments, "#Q=[hold](current)next".
+
+use std::io;
+
+use crate::game::{Board, Cell, Command, HEIGHT, Setup, WIDTH};
+use crate::movegen;
+use crate::replay::Replay;
+use crate::tbp::{Location, Orientation, Piece};
+
+pub const URL_PREFIX: &str = "https://fumen.zui.jp/?";
+const VERSION_PREFIX: &str = "v115@";
+const QUIZ_PREFIX: &str = "#Q=";
+
+const ENCODE_TABLE: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+// Comments are stored as indices into this table, four characters to five
+// base64 digits.
+const COMMENT_TABLE: &[u8; 95] =
+    b" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+const COMMENT_BASE: u32 = COMMENT_TABLE.len() as u32 + 1;
+const MAX_COMMENT_LEN: usize = 4095;
+
+// Rows of a fumen field from the top, the last being the garbage row.
+const FIELD_TOP: usize = 23;
+const FIELD_ROWS: usize = FIELD_TOP + 1;
+const FIELD_BLOCKS: u32 = FIELD_ROWS as u32 * WIDTH as u32;
+// How far down a fumen field this game's board starts.
+const BOARD_OFFSET: usize = FIELD_TOP - HEIGHT as usize;
+// The fumen editor breaks data up with a ? every 47 characters, counting
+// the version prefix in the first stretch.
+const CHUNK_LEN: usize = 47;
+
+type Field = [[u8; WIDTH as usize]; FIELD_ROWS];
+
+const EMPTY_FIELD: Field = [[0; WIDTH as usize]; FIELD_ROWS];
+
+// One page of a fumen: the field, a piece drawn over it and a comment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page {
+    field: Field,
+    // Where the piece is, in the same terms as TBP, which fumen shares.
+    pub piece: Option<Location>,
+    // Whether the piece locks into the field carried on to the next page.
+    pub lock: bool,
+    pub comment: String,
+}
+
+impl Page {
+    pub fn new(board: &Board, piece: Option<Location>, comment: String) -> Self {
+        let mut field = EMPTY_FIELD;
+        for (row, cells) in field[BOARD_OFFSET..FIELD_TOP].iter_mut().zip(board) {
+            *row = cells.map(cell_code);
+        }
+        Self { field, piece, lock: true, comment }
+    }
+
+    // The setup's board, with its pieces written into a quiz comment.
+    pub fn from_setup(setup: &Setup) -> Self {
+        let quiz = Quiz {
+            hold: setup.hold.as_ref().map(Piece::of),
+            current: setup.current.as_ref().map(Piece::of),
+            queue: setup.queue.iter().map(Piece::of).collect(),
+        };
+        let comment = if quiz.is_empty() { String::new() } else { quiz.to_string() };
+        Self::new(&setup.board, None, comment)
+    }
+
+    pub fn board(&self) -> io::Result<Board> {
+        if self.field[..BOARD_OFFSET].iter().flatten().any(|&code| code != 0) {
+            return Err(fumen_error("the field is taller than this game's board"));
+        }
+        let mut board = [[Cell::Empty; WIDTH as usize]; HEIGHT as usize];
+        for (cells, row) in board.iter_mut().zip(&self.field[BOARD_OFFSET..FIELD_TOP]) {
+            *cells = row.map(code_cell);
+        }
+        Ok(board)
+    }
+
+    // The position to play from. The pieces come from a quiz comment if there
+    // is one, and otherwise the page's piece is the one to play.
+    pub fn setup(&self) -> io::Result<Setup> {
+        let board = self.board()?;
+        Ok(match Quiz::parse(&self.comment) {
+            Some(quiz) => Setup {
+                board,
+                current: quiz.current.map(Piece::tetromino),
+                queue: quiz.queue.into_iter().map(Piece::tetromino).collect(),
+                hold: quiz.hold.map(Piece::tetromino),
+            },
+            None => Setup {
+                board,
+                current: self.piece.map(|location| location.piece.tetromino()),
+                ..Setup::default()
+            },
+        })
+    }
+
+    // The field the next page starts from, if it doesn't change it.
+    fn next_field(&self) -> Field {
+        let mut field = self.field;
+        if !self.lock {
+            return field;
+        }
+        if let Some(location) = &self.piece {
+            for (x, y) in cells(location) {
+                if let Some(row) = row_of(y) {
+                    field[row][x as usize] = piece_code(location.piece);
+                }
+            }
+        }
+        let mut rows: Vec<_> = field[..FIELD_TOP].iter()
+            .filter(|row| row.contains(&0))
+            .copied()
+            .collect();
+        while rows.len() < FIELD_TOP {
+            rows.insert(0, [0; WIDTH as usize]);
+        }
+        field[..FIELD_TOP].copy_from_slice(&rows);
+        field
+    }
+}
+
+// The pages of a fumen, given either by itself or as a URL.
+pub fn decode(data: &str) -> io::Result<Vec<Page>> {
+    let start = data.find(VERSION_PREFIX)
+        .ok_or_else(|| fumen_error("expected v115 data"))?;
+    let mut reader = Reader::new(&data[start + VERSION_PREFIX.len()..])?;
+    let mut pages: Vec<Page> = Vec::new();
+    let mut field = EMPTY_FIELD;
+    let mut repeats = 0;
+    let mut comment = String::new();
+    while !reader.is_empty() {
+        if repeats > 0 {
+            repeats -= 1;
+        } else if !read_field(&mut reader, &mut field)? {
+            repeats = reader.read(1)?;
+        }
+
+        let action = reader.read(3)?;
+        let kind = action % 8;
+        let rotation = action / 8 % 4;
+        let position = action / 32 % FIELD_BLOCKS;
+        let rise = action / 7680 % 2 == 1;
+        let mirror = action / 15360 % 2 == 1;
+        let has_comment = action / 61440 % 2 == 1;
+        let lock = action / 122880 % 2 == 0;
+
+        if has_comment {
+            comment = read_comment(&mut reader)?;
+        }
+        let piece = match piece_of_code(kind as u8) {
+            Some(piece) => {
+                let orientation = ORIENTATIONS[rotation as usize];
+                let (dx, dy) = position_shift(piece, orientation);
+                let location = Location {
+                    piece,
+                    orientation,
+                    x: (position % WIDTH as u32) as i32 - dx,
+                    y: FIELD_TOP as i32 - 1 - (position / WIDTH as u32) as i32 - dy,
+                };
+                let on_field = |(x, y)| (0..WIDTH).contains(&x) && row_of(y).is_some();
+                if !cells(&location).into_iter().all(on_field) {
+                    return Err(fumen_error("a piece is off the field"));
+                }
+                Some(location)
+            }
+            None => None,
+        };
+        let page = Page { field, piece, lock, comment: comment.clone() };
+
+        field = page.next_field();
+        if lock {
+            if rise {
+                field.copy_within(1.., 0);
+                field[FIELD_TOP] = [0; WIDTH as usize];
+            }
+            if mirror {
+                field[..FIELD_TOP].iter_mut().for_each(|row| row.reverse());
+            }
+            if let (Some(location), Some(mut quiz)) = (&piece, Quiz::parse(&comment)) {
+                quiz.play(location.piece);
+                comment = quiz.to_string();
+            }
+        }
+        pages.push(page);
+    }
+    if pages.is_empty() {
+        return Err(fumen_error("there are no pages"));
+    }
+    Ok(pages)
+}
+
+// The setup on one page of fumen data or a fumen URL, counting pages from 1.
+pub fn import(data: &str, page: usize) -> io::Result<Setup> {
+    let pages = decode(data)?;
+    page.checked_sub(1)
+        .and_then(|index| pages.get(index))
+        .ok_or_else(|| fumen_error(&format!("there's no page {page}")))?
+        .setup()
+}
+
+// Packs pages into fumen data, without the URL in front.
+pub fn encode(pages: &[Page]) -> String {
+    let mut writer = Writer::default();
+    let mut field = EMPTY_FIELD;
+    let mut comment = String::new();
+    // Where the count of pages with the same field as the one before is.
+    let mut repeat_at: Option<usize> = None;
+    for (index, page) in pages.iter().enumerate() {
+        let (values, changed) = write_field(&field, &page.field);
+        match repeat_at {
+            _ if changed => {
+                writer.extend(values);
+                repeat_at = None;
+            }
+            Some(at) if writer.digits[at] < 63 => writer.digits[at] += 1,
+            _ => {
+                writer.extend(values);
+                writer.write(0, 1);
+                repeat_at = Some(writer.digits.len() - 1);
+            }
+        }
+
+        let has_comment = page.comment != comment;
+        let (kind, rotation, position) = match &page.piece {
+            Some(location) => {
+                let (dx, dy) = position_shift(location.piece, location.orientation);
+                let (x, y) = (location.x + dx, location.y + dy);
+                let rotation = ORIENTATIONS.iter()
+                    .position(|&orientation| orientation == location.orientation)
+                    .expect("every orientation has a code");
+                let position = (FIELD_TOP as i32 - 1 - y) * WIDTH + x;
+                (piece_code(location.piece) as u32, rotation as u32, position as u32)
+            }
+            None => (0, 0, 0),
+        };
+        let flags = [!page.lock, has_comment, index == 0, false, false]
+            .iter()
+            .fold(0, |flags, &flag| flags * 2 + flag as u32);
+        writer.write(((flags * FIELD_BLOCKS + position) * 4 + rotation) * 8 + kind, 3);
+        if has_comment {
+            write_comment(&mut writer, &page.comment);
+        }
+
+        comment = page.comment.clone();
+        if let (true, Some(location), Some(mut quiz)) =
+            (page.lock, &page.piece, Quiz::parse(&comment))
+        {
+            quiz.play(location.piece);
+            comment = quiz.to_string();
+        }
+        field = page.next_field();
+    }
+
+    let data = format!("{VERSION_PREFIX}{}", writer.to_base64());
+    let chunks: Vec<&str> = data.as_bytes()
+        .chunks(CHUNK_LEN)
+        .map(|chunk| std::str::from_utf8(chunk).expect("fumen data is ascii"))
+        .collect();
+    chunks.join("?")
+}
+
+// One page for every piece placed in the replay, showing it as it locks,
+// then one for the board it finished on.
+pub fn replay_pages(replay: &Replay) -> Vec<Page> {
+    let mut game = replay.new_game();
+    let mut pages = Vec::new();
+    for command in replay.commands() {
+        let board = *game.board();
+        let active_piece = game.active_piece().cloned();
+        let pieces = game.render_pieces();
+        game.tick(command);
+        if game.render_pieces() <= pieces || command == Command::Redo {
+            continue;
+        }
+        // The piece can only have locked where the command left it, once it
+        // had fallen as far as it could.
+        let piece = active_piece.map(|piece| {
+            let mut piece = movegen::apply(&piece, command, &board);
+            piece.drop_to_floor(&board);
+            Location::of(&piece)
+        });
+        pages.push(Page::new(&board, piece, String::new()));
+    }
+    pages.push(Page::new(game.board(), None, String::new()));
+    pages
+}
+
+// Reads a field stored as its change from `field`, into `field`. Returns
+// false if it didn't change at all.
+fn read_field(reader: &mut Reader, field: &mut Field) -> io::Result<bool> {
+    let mut changed = true;
+    let mut index = 0;
+    while index < FIELD_BLOCKS {
+        let value = reader.read(2)?;
+        let diff = value / FIELD_BLOCKS;
+        let count = value % FIELD_BLOCKS + 1;
+        if diff == 8 && count == FIELD_BLOCKS {
+            changed = false;
+        }
+        for _ in 0..count {
+            let Some(row) = field.get_mut((index / WIDTH as u32) as usize) else {
+                return Err(fumen_error("the field runs over"));
+            };
+            let cell = &mut row[(index % WIDTH as u32) as usize];
+            *cell = (*cell as u32 + diff).checked_sub(8)
+                .filter(|&code| code <= 8)
+                .ok_or_else(|| fumen_error("a block in the field isn't valid"))? as u8;
+            index += 1;
+        }
+    }
+    Ok(changed)
+}
+
+// Stores `field` as runs of the same change from `previous`. Also returns
+// whether anything changed.
+fn write_field(previous: &Field, field: &Field) -> (Vec<u8>, bool) {
+    let mut writer = Writer::default();
+    let diffs: Vec<u32> = field.iter().flatten()
+        .zip(previous.iter().flatten())
+        .map(|(&code, &previous)| code as u32 + 8 - previous as u32)
+        .collect();
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for diff in diffs {
+        match runs.last_mut() {
+            Some((last, count)) if *last == diff => *count += 1,
+            _ => runs.push((diff, 1)),
+        }
+    }
+    for &(diff, count) in &runs {
+        writer.write(diff * FIELD_BLOCKS + count - 1, 2);
+    }
+    (writer.digits, runs != [(8, FIELD_BLOCKS)])
+}
+
+fn read_comment(reader: &mut Reader) -> io::Result<String> {
+    let len = reader.read(2)? as usize;
+    let mut escaped = String::new();
+    for _ in 0..len.div_ceil(4) {
+        let mut value = reader.read(5)?;
+        for _ in 0..4 {
+            let index = (value % COMMENT_BASE) as usize;
+            let char = COMMENT_TABLE.get(index)
+                .ok_or_else(|| fumen_error("a comment character isn't valid"))?;
+            escaped.push(*char as char);
+            value /= COMMENT_BASE;
+        }
+    }
+    escaped.truncate(len);
+    unescape(&escaped)
+}
+
+fn write_comment(writer: &mut Writer, comment: &str) {
+    let mut escaped = escape(comment);
+    escaped.truncate(MAX_COMMENT_LEN);
+    writer.write(escaped.len() as u32, 2);
+    for chunk in escaped.as_bytes().chunks(4) {
+        let value = chunk.iter().rev().fold(0, |value, char| {
+            let index = COMMENT_TABLE.iter().position(|c| c == char).unwrap_or(0);
+            value * COMMENT_BASE + index as u32
+        });
+        writer.write(value, 5);
+    }
+}
+
+// Comments are escaped the way JavaScript's escape() does it: anything but
+// letters, digits and @*_+-./ becomes %XX, or %uXXXX past Latin-1.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for unit in text.encode_utf16() {
+        match char::from_u32(unit as u32) {
+            Some(char) if char.is_ascii_alphanumeric() || "@*_+-./".contains(char) => {
+                escaped.push(char);
+            }
+            _ if unit < 256 => escaped.push_str(&format!("%{unit:02X}")),
+            _ => escaped.push_str(&format!("%u{unit:04X}")),
+        }
+    }
+    escaped
+}
+
+fn unescape(escaped: &str) -> io::Result<String> {
+    let mut units = Vec::new();
+    let mut rest = escaped;
+    while let Some(char) = rest.chars().next() {
+        let (unit, len) = match (char, rest.get(1..2)) {
+            ('%', Some("u")) => (rest.get(2..6).and_then(|hex| u16::from_str_radix(hex, 16).ok()), 6),
+            ('%', _) => (rest.get(1..3).and_then(|hex| u16::from_str_radix(hex, 16).ok()), 3),
+            _ => (Some(char as u16), 1),
+        };
+        units.push(unit.ok_or_else(|| fumen_error("a comment isn't escaped properly"))?);
+        rest = &rest[len..];
+    }
+    String::from_utf16(&units).map_err(|_| fumen_error("a comment isn't valid text"))
+}
+
+// The pieces in a quiz comment: the held piece, the one to play now and the
+// ones after it.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Quiz {
+    hold: Option<Piece>,
+    current: Option<Piece>,
+    queue: Vec<Piece>,
+}
+
+impl Quiz {
+    fn parse(comment: &str) -> Option<Self> {
+        let rest = comment.strip_prefix(QUIZ_PREFIX)?;
+        let (hold, rest) = rest.strip_prefix('[')?.split_once(']')?;
+        let (current, rest) = rest.strip_prefix('(')?.split_once(')')?;
+        let optional = |letters: &str| match letters {
+            "" => Some(None),
+            letters => letters.parse::<char>().ok().and_then(piece_of_letter).map(Some),
+        };
+        Some(Self {
+            hold: optional(hold)?,
+            current: optional(current)?,
+            queue: rest.chars().map_while(piece_of_letter).collect(),
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // Moves on to the next piece after `piece` is played, holding if that's
+    // the only way it could have been.
+    fn play(&mut self, piece: Piece) {
+        if self.current == Some(piece) {
+            self.current = self.next();
+        } else if self.hold == Some(piece) {
+            self.hold = self.current;
+            self.current = self.next();
+        } else if self.hold.is_none() && self.queue.first() == Some(&piece) {
+            self.hold = self.current;
+            self.queue.remove(0);
+            self.current = self.next();
+        }
+    }
+
+    fn next(&mut self) -> Option<Piece> {
+        (!self.queue.is_empty()).then(|| self.queue.remove(0))
+    }
+}
+
+impl std::fmt::Display for Quiz {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let letters = |pieces: &[Piece]| -> String {
+            pieces.iter().map(|&piece| letter(piece)).collect()
+        };
+        write!(
+            f,
+            "{QUIZ_PREFIX}[{}]({}){}",
+            letters(self.hold.as_slice()),
+            letters(self.current.as_slice()),
+            letters(&self.queue),
+        )
+    }
+}
+
+// Reads base64 digits, least significant first.
+struct Reader {
+    digits: Vec<u32>,
+    position: usize,
+}
+
+impl Reader {
+    // Takes digits up to the end of the data, skipping the ?s in between.
+    fn new(data: &str) -> io::Result<Self> {
+        let digits = data.bytes()
+            .filter(|&byte| byte != b'?')
+            .take_while(|byte| !matches!(byte, b'#' | b'&') && !byte.is_ascii_whitespace())
+            .map(|byte| {
+                ENCODE_TABLE.iter()
+                    .position(|&digit| digit == byte)
+                    .map(|digit| digit as u32)
+                    .ok_or_else(|| fumen_error(&format!("unexpected character {:?}", byte as char)))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self { digits, position: 0 })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.digits.len()
+    }
+
+    fn read(&mut self, digits: usize) -> io::Result<u32> {
+        let end = self.position + digits;
+        let value = self.digits.get(self.position..end)
+            .ok_or_else(|| fumen_error("the data ends too soon"))?
+            .iter()
+            .rev()
+            .fold(0, |value, digit| value * 64 + digit);
+        self.position = end;
+        Ok(value)
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    digits: Vec<u8>,
+}
+
+impl Writer {
+    fn write(&mut self, mut value: u32, digits: usize) {
+        for _ in 0..digits {
+            self.digits.push((value % 64) as u8);
+            value /= 64;
+        }
+    }
+
+    fn extend(&mut self, digits: Vec<u8>) {
+        self.digits.extend(digits);
+    }
+
+    fn to_base64(&self) -> String {
+        self.digits.iter().map(|&digit| ENCODE_TABLE[digit as usize] as char).collect()
+    }
+}
+
+// In the order of their codes.
+const ORIENTATIONS: [Orientation; 4] =
+    [Orientation::South, Orientation::East, Orientation::North, Orientation::West];
+
+// The cells a piece covers, as (x, y) from the bottom left. Unlike in TBP,
+// an O piece turns around its bottom left cell.
+fn cells(location: &Location) -> [(i32, i32); 4] {
+    let north = match location.piece {
+        Piece::I => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+        Piece::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+        Piece::T => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+        Piece::L => [(-1, 0), (0, 0), (1, 0), (1, 1)],
+        Piece::J => [(-1, 0), (0, 0), (1, 0), (-1, 1)],
+        Piece::S => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+        Piece::Z => [(-1, 1), (0, 1), (0, 0), (1, 0)],
+    };
+    north.map(|(x, y)| {
+        let (dx, dy) = match location.orientation {
+            Orientation::North => (x, y),
+            Orientation::East => (y, -x),
+            Orientation::South => (-x, -y),
+            Orientation::West => (-y, x),
+        };
+        (location.x + dx, location.y + dy)
+    })
+}
+
+// Fumen stores some pieces by a different cell than the one they turn
+// around, always one that's part of the piece. This is how far that cell is
+// from it.
+fn position_shift(piece: Piece, orientation: Orientation) -> (i32, i32) {
+    match (piece, orientation) {
+        (Piece::O, Orientation::West) => (-1, 1),
+        (Piece::O, Orientation::South) => (-1, 0),
+        (Piece::O, Orientation::North) => (0, 1),
+        (Piece::I, Orientation::South) => (-1, 0),
+        (Piece::I, Orientation::West) => (0, 1),
+        (Piece::S, Orientation::North) => (0, 1),
+        (Piece::S, Orientation::East) => (1, 0),
+        (Piece::Z, Orientation::North) => (0, 1),
+        (Piece::Z, Orientation::West) => (-1, 0),
+        _ => (0, 0),
+    }
+}
+
+// The field row y cells up from the bottom is in, if it's on the field.
+fn row_of(y: i32) -> Option<usize> {
+    (0..FIELD_TOP as i32).contains(&y).then(|| FIELD_TOP - 1 - y as usize)
+}
+
+const PIECE_CODES: [(Piece, u8); 7] = [
+    (Piece::I, 1),
+    (Piece::L, 2),
+    (Piece::O, 3),
+    (Piece::Z, 4),
+    (Piece::T, 5),
+    (Piece::J, 6),
+    (Piece::S, 7),
+];
+const GARBAGE_CODE: u8 = 8;
+
+fn piece_code(piece: Piece) -> u8 {
+    PIECE_CODES.iter()
+        .find(|(other, _)| *other == piece)
+        .map(|&(_, code)| code)
+        .expect("every piece has a code")
+}
+
+fn piece_of_code(code: u8) -> Option<Piece> {
+    PIECE_CODES.iter().find(|(_, other)| *other == code).map(|&(piece, _)| piece)
+}
+
+fn cell_code(cell: Cell) -> u8 {
+    match cell {
+        Cell::Empty => 0,
+        Cell::IBlock => piece_code(Piece::I),
+        Cell::JBlock => piece_code(Piece::J),
+        Cell::LBlock => piece_code(Piece::L),
+        Cell::OBlock => piece_code(Piece::O),
+        Cell::SBlock => piece_code(Piece::S),
+        Cell::TBlock => piece_code(Piece::T),
+        Cell::ZBlock => piece_code(Piece::Z),
+        _ => GARBAGE_CODE,
+    }
+}
+
+fn code_cell(code: u8) -> Cell {
+    match piece_of_code(code) {
+        Some(piece) => piece.tetromino().get_cell_type(),
+        None if code == 0 => Cell::Empty,
+        None => Cell::Garbage,
+    }
+}
+
+fn letter(piece: Piece) -> char {
+    match piece {
+        Piece::I => 'I',
+        Piece::O => 'O',
+        Piece::T => 'T',
+        Piece::L => 'L',
+        Piece::J => 'J',
+        Piece::S => 'S',
+        Piece::Z => 'Z',
+    }
+}
+
+fn piece_of_letter(letter: char) -> Option<Piece> {
+    PIECE_CODES.iter()
+        .map(|&(piece, _)| piece)
+        .find(|&piece| self::letter(piece) == letter)
+}
+
+fn fumen_error(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Fumen: {reason}"))
+}
@@ -1,81 +1,634 @@
-use std::time::{Duration, Instant};
+use std::collections::VecDeque;
+use std::time::Duration;
 
+use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::attack::{self, Clear};
+use crate::finesse;
+use crate::garbage::{GarbageGenerator, GarbageRules};
+use crate::mode::{
+    DIG_VISIBLE_ROWS, FRAMES_PER_SECOND, GRAVITY_20G, MASTER_MAX_LEVEL, Mode,
+};
 use crate::PositionedTetromino;
+use crate::randomizer::{Dealer, Randomizer};
+use crate::stats::Stats;
 use crate::tetromino::Tetromino;
 
 pub const HEIGHT: i32 = 20;
 pub const WIDTH: i32 = 10;
 
-pub type Board = [[Cell; WIDTH as usize]; HEIGHT as usize];
+pub type Row = [Cell; WIDTH as usize];
+pub type Board = [Row; HEIGHT as usize];
 pub type NextPiece = [[Cell; 4]; 2];
 
+// A position to start a game from instead of an empty board: the stack, the
+// piece to play first and the ones to deal after it. There's no hold in this
+// game, so a held piece is only kept to be passed on, as when a setup imported
+// from fumen is exported again.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Setup {
+    pub board: Board,
+    pub current: Option<Tetromino>,
+    pub queue: Vec<Tetromino>,
+    pub hold: Option<Tetromino>,
+}
+
 pub const PIECE_START_Y: i32 = 1;
 pub const PIECE_START_X: i32 = 5;
-const INITIAL_DROP_INTERVAL: Duration = Duration::from_millis(500);
 
-#[derive(Debug, PartialEq)]
+// The 64-bit FNV-1a parameters, for `Game::state_hash`.
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// How long the stack is shown after a line clear in invisible mode, and how
+// long blocks spend fading out before they disappear.
+const FLASH_FRAMES: u64 = 20;
+const FADE_FRAMES: u64 = 30;
+
+// All timing is counted in frames, with one frame per call to `Game::tick`,
+// and all randomness comes from the seeded rng, so a game is fully determined
+// by its mode, seed and the commands fed to it.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Game {
     debug_msg: String,
+    seed: u64,
+    // Where the game started from, if not an empty board.
+    setup: Option<Setup>,
+    rng: ChaCha8Rng,
+    dealer: Dealer,
+    settled_pieces: Board,
+    // Frame on which each settled cell was locked.
+    locked_at: [[u64; WIDTH as usize]; HEIGHT as usize],
+    active_piece: Option<PositionedTetromino>,
+    next_piece: Tetromino,
+    score: i32,
+    mode: Mode,
+    level: u32,
+    lines: u32,
+    pieces: u32,
+    frame: u64,
+    finished: Option<u64>,
+    // Fraction of a cell the active piece has fallen since its last drop.
+    gravity_progress: f64,
+    // Frames the active piece has spent resting on the stack.
+    lock_timer: u32,
+    // Frames left until the next piece spawns, while there is no active piece.
+    spawn_delay: u32,
+    // Frame until which the whole stack is shown in invisible mode.
+    flash_until: u64,
+    garbage: GarbageGenerator,
+    garbage_pending: u32,
+    // Garbage sent by an opponent that hasn't reached the board yet, in the
+    // batches it was sent in, and the rules for when it gets there.
+    incoming: VecDeque<Incoming>,
+    garbage_rules: GarbageRules,
+    // Lines of garbage sent to the opponent that haven't been collected by
+    // `take_attack` yet.
+    outgoing: u32,
+    history: History,
+    stats: Stats,
+    // Number of clears in the current chain, minus one. None if the last
+    // piece didn't clear anything.
+    combo: Option<u32>,
+    back_to_back: bool,
+    // Whether the active piece's last successful move was a rotation, for
+    // spotting T-spins.
+    last_move_rotated: bool,
+    // Where the active piece spawned and the finesse inputs spent on it so
+    // far, to compare against the shortest way to where it ends up.
+    piece_start: Option<PositionedTetromino>,
+    piece_inputs: u32,
+    // Whether finesse faults are counted outside finesse mode.
+    counts_finesse: bool,
+    // Placement the active piece should go to in finesse mode, and the
+    // shortest way there from where the piece was when it was worked out.
+    target: Option<PositionedTetromino>,
+    target_inputs: Option<(PositionedTetromino, String)>,
+}
+
+// A batch of garbage from an opponent, and the frame it can rise from.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+struct Incoming {
+    lines: u32,
+    ready_at: u64,
+}
+
+// The state needed to rewind to the moment a piece spawned.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Snapshot {
     settled_pieces: Board,
     active_piece: Option<PositionedTetromino>,
     next_piece: Tetromino,
-    last_drop: Instant,
     score: i32,
-    drop_interval: Duration,
+    level: u32,
+    lines: u32,
+    pieces: u32,
+}
+
+// Undo and redo stacks of placements, only kept in modes that allow undo.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct History {
+    current: Option<Snapshot>,
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Game {
     pub fn new() -> Self {
-        let settled_pieces = [[Cell::Empty; WIDTH as usize]; HEIGHT as usize];
-        let first_piece = Tetromino::new(None);
-        Self {
+        Self::with_mode(Mode::default())
+    }
+
+    pub fn with_mode(mode: Mode) -> Self {
+        Self::with_seed(mode, rand::random())
+    }
+
+    pub fn with_seed(mode: Mode, seed: u64) -> Self {
+        Self::with_randomizer(mode, seed, Randomizer::default())
+    }
+
+    pub fn with_randomizer(mode: Mode, seed: u64, randomizer: Randomizer) -> Self {
+        Self::start(mode, seed, randomizer, None)
+    }
+
+    // Starts from the setup's board and pieces, with the randomizer dealing
+    // once they run out.
+    pub fn with_setup(mode: Mode, seed: u64, randomizer: Randomizer, setup: Setup) -> Self {
+        Self::start(mode, seed, randomizer, Some(setup))
+    }
+
+    fn start(mode: Mode, seed: u64, randomizer: Randomizer, setup: Option<Setup>) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let level = mode.start_level();
+        let (garbage, garbage_pending) = match &mode {
+            Mode::Dig(dig) => (GarbageGenerator::new(dig.messiness, &mut rng), dig.lines),
+            _ => (GarbageGenerator::new(0.0, &mut rng), 0),
+        };
+        let (settled_pieces, queue) = match &setup {
+            Some(setup) => {
+                let queue = setup.current.iter().chain(&setup.queue).cloned().collect();
+                (setup.board, queue)
+            }
+            None => ([[Cell::Empty; WIDTH as usize]; HEIGHT as usize], Vec::new()),
+        };
+        let mut dealer = Dealer::with_queue(randomizer, queue);
+        let next_piece = dealer.deal(None, &mut rng);
+        let mut game = Self {
             debug_msg: String::from("Welcome to Tetris!"),
+            seed,
+            setup,
+            rng,
+            dealer,
             settled_pieces,
-            active_piece: PositionedTetromino::place(&first_piece, &settled_pieces),
-            next_piece: Tetromino::new(Some(&first_piece)),
-            last_drop: Instant::now(),
+            locked_at: [[0; WIDTH as usize]; HEIGHT as usize],
+            active_piece: None,
+            next_piece,
             score: 0,
-            drop_interval: INITIAL_DROP_INTERVAL,
-        }
+            mode,
+            level,
+            lines: 0,
+            pieces: 0,
+            frame: 0,
+            finished: None,
+            gravity_progress: 0.0,
+            lock_timer: 0,
+            spawn_delay: 0,
+            flash_until: 0,
+            garbage,
+            garbage_pending,
+            incoming: VecDeque::new(),
+            garbage_rules: GarbageRules::default(),
+            outgoing: 0,
+            history: History::default(),
+            stats: Stats::default(),
+            combo: None,
+            back_to_back: false,
+            last_move_rotated: false,
+            piece_start: None,
+            piece_inputs: 0,
+            counts_finesse: true,
+            target: None,
+            target_inputs: None,
+        };
+        game.refill_garbage();
+        game.spawn_next_piece();
+        game
     }
 
     pub fn tick(&mut self, command: Command) {
+        self.step(command);
+        if matches!(self.mode, Mode::Finesse) {
+            self.update_target_inputs();
+        }
+    }
+
+    fn step(&mut self, command: Command) {
+        if self.is_over() {
+            return;
+        }
+        self.frame += 1;
+
+        match command {
+            Command::Undo => return self.undo(),
+            Command::Redo => return self.redo(),
+            Command::NoOp => (),
+            _ => self.stats.keys += 1,
+        }
+
+        if self.active_piece.is_none() {
+            self.spawn_delay = self.spawn_delay.saturating_sub(1);
+            if self.spawn_delay > 0 {
+                return;
+            }
+            self.spawn_next_piece();
+        }
+
         if let Some(ref mut active_piece) = self.active_piece {
+            let before = active_piece.clone();
+            let mut hard_dropped = false;
+            if finesse::is_finesse_input(&command) {
+                self.piece_inputs += 1;
+            }
             match &command {
                 Command::CounterClockwise => active_piece.rotate_ccw(&self.settled_pieces),
                 Command::Clockwise => active_piece.rotate_cw(&self.settled_pieces),
                 Command::Left => active_piece.move_left(&self.settled_pieces),
                 Command::Right => active_piece.move_right(&self.settled_pieces),
-                Command::Drop => active_piece.move_down(&self.settled_pieces),
+                Command::Drop if active_piece.can_move_down(&self.settled_pieces) => {
+                    active_piece.move_down(&self.settled_pieces);
+                    self.lock_timer = 0;
+                }
+                Command::HardDrop => {
+                    active_piece.drop_to_floor(&self.settled_pieces);
+                    hard_dropped = true;
+                }
                 Command::Up => active_piece.move_up(&self.settled_pieces),
                 Command::ChangePiece => active_piece.change(),
                 _ => (),
             };
+            if *active_piece != before {
+                self.last_move_rotated =
+                    matches!(command, Command::Clockwise | Command::CounterClockwise);
+            }
+            if hard_dropped {
+                return self.lock_active_piece();
+            }
 
-            if self.last_drop.elapsed() >= self.drop_interval {
-                if active_piece.can_move_down(&self.settled_pieces) {
-                    active_piece.move_down(&self.settled_pieces);
-                } else {
-                    for (y, x) in active_piece.get_coords() {
-                        self.settled_pieces[y as usize][x as usize] = active_piece.get_cell_type();
-                    }
-                    self.clear_full_rows();
+            // The lock timer only resets when the piece steps down a row, so
+            // sliding on and off a ledge can't stall it forever.
+            self.gravity_progress += self.mode.gravity(self.level, self.score);
+            while self.gravity_progress >= 1.0 && active_piece.can_move_down(&self.settled_pieces) {
+                active_piece.move_down(&self.settled_pieces);
+                self.gravity_progress -= 1.0;
+                self.lock_timer = 0;
+                self.last_move_rotated = false;
+            }
 
-                    self.active_piece = PositionedTetromino::place(
-                        &self.next_piece, &self.settled_pieces,
-                    );
-                    self.next_piece = Tetromino::new(Some(&self.next_piece));
+            if !active_piece.can_move_down(&self.settled_pieces) {
+                self.gravity_progress = 0.0;
+                self.lock_timer += 1;
+                if self.lock_timer >= self.mode.timings(self.level).lock_delay {
+                    self.lock_active_piece();
+                }
+            }
+        }
+    }
 
-                    if self.active_piece == None {
-                        self.debug_msg = String::from("You lost!");
-                    }
+    fn spawn_next_piece(&mut self) {
+        self.active_piece = PositionedTetromino::place(
+            &self.next_piece, &self.settled_pieces,
+        );
+        self.next_piece = self.dealer.deal(Some(&self.next_piece), &mut self.rng);
+        self.gravity_progress = 0.0;
+        self.lock_timer = 0;
+        self.last_move_rotated = false;
+        self.piece_inputs = 0;
+
+        // Master levels go up with every piece, but stop at the end of each
+        // section until a line clear pushes them over.
+        if matches!(self.mode, Mode::Master)
+            && self.level % 100 != 99 && self.level < MASTER_MAX_LEVEL - 1 {
+            self.level += 1;
+        }
+
+        if self.active_piece.is_none() && self.mode.clears_on_top_out() {
+            self.settled_pieces = [[Cell::Empty; WIDTH as usize]; HEIGHT as usize];
+            self.debug_msg = String::from("Board cleared!");
+            self.active_piece = PositionedTetromino::place(
+                &self.next_piece, &self.settled_pieces,
+            );
+            self.next_piece = self.dealer.deal(Some(&self.next_piece), &mut self.rng);
+        }
+
+        match self.active_piece {
+            None => self.end("You lost!"),
+            Some(ref mut active_piece) => {
+                if self.mode.gravity(self.level, self.score) >= GRAVITY_20G {
+                    active_piece.drop_to_floor(&self.settled_pieces);
                 }
-                self.last_drop += self.drop_interval;
             }
         }
+        self.piece_start = self.active_piece.clone();
+        if matches!(self.mode, Mode::Finesse) {
+            self.pick_target();
+        }
+
+        if self.mode.can_undo() {
+            self.history.current = Some(self.snapshot());
+        }
     }
 
-    fn clear_full_rows(&mut self) {
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            settled_pieces: self.settled_pieces,
+            active_piece: self.active_piece.clone(),
+            next_piece: self.next_piece.clone(),
+            score: self.score,
+            level: self.level,
+            lines: self.lines,
+            pieces: self.pieces,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.settled_pieces = snapshot.settled_pieces;
+        self.active_piece = snapshot.active_piece;
+        self.next_piece = snapshot.next_piece;
+        self.score = snapshot.score;
+        self.level = snapshot.level;
+        self.lines = snapshot.lines;
+        self.pieces = snapshot.pieces;
+        self.gravity_progress = 0.0;
+        self.lock_timer = 0;
+        self.spawn_delay = 0;
+        self.piece_start = self.active_piece.clone();
+        self.piece_inputs = 0;
+        self.target_inputs = None;
+    }
+
+    // Picks one of the spots the active piece can be hard dropped into as
+    // the target for finesse practice.
+    fn pick_target(&mut self) {
+        self.target_inputs = None;
+        self.target = self.active_piece.as_ref().and_then(|active_piece| {
+            let targets = finesse::hard_drop_targets(active_piece, &self.settled_pieces);
+            targets.choose(&mut self.rng).cloned()
+        });
+    }
+
+    // Works out the way to the finesse target again, once the piece has
+    // moved from where it was last worked out from.
+    fn update_target_inputs(&mut self) {
+        let (Some(active_piece), Some(target)) = (&self.active_piece, &self.target) else {
+            self.target_inputs = None;
+            return;
+        };
+        if self.target_inputs.as_ref().is_some_and(|(from, _)| from == active_piece) {
+            return;
+        }
+        self.target_inputs = finesse::optimal_inputs(active_piece, target, &self.settled_pieces)
+            .map(|path| (active_piece.clone(), finesse::format_inputs(&path)));
+    }
+
+    // Rewinds to when the previously placed piece spawned.
+    fn undo(&mut self) {
+        let Some(snapshot) = self.history.undo.pop() else {
+            return;
+        };
+        if let Some(current) = self.history.current.replace(snapshot.clone()) {
+            self.history.redo.push(current);
+        }
+        self.restore(snapshot);
+        self.debug_msg = String::from("Undo");
+    }
+
+    fn redo(&mut self) {
+        let Some(snapshot) = self.history.redo.pop() else {
+            return;
+        };
+        if let Some(current) = self.history.current.replace(snapshot.clone()) {
+            self.history.undo.push(current);
+        }
+        self.restore(snapshot);
+        self.debug_msg = String::from("Redo");
+    }
+
+    fn lock_active_piece(&mut self) {
+        let Some(active_piece) = self.active_piece.take() else {
+            return;
+        };
+        let t_spin = self.last_move_rotated
+            && active_piece.is_t_spin_position(&self.settled_pieces);
+        let feedback = self.check_finesse(&active_piece);
+        for (y, x) in active_piece.get_coords() {
+            self.settled_pieces[y as usize][x as usize] = active_piece.get_cell_type();
+            self.locked_at[y as usize][x as usize] = self.frame;
+        }
+        self.pieces += 1;
+        if let Some(placed) = self.history.current.take() {
+            self.history.undo.push(placed);
+            self.history.redo.clear();
+        }
+        let num_cleared = self.clear_full_rows();
+        let clear = Clear {
+            lines: num_cleared,
+            t_spin,
+            perfect_clear: num_cleared > 0 && self.settled_pieces.iter()
+                .all(|row| row.iter().all(|cell| *cell == Cell::Empty)),
+        };
+        let attack = self.record_clear(&clear);
+        self.outgoing += self.cancel_incoming(attack);
+        if let Some(feedback) = feedback {
+            self.debug_msg = feedback;
+        }
+        // Incoming garbage waits while the player keeps clearing lines.
+        if !self.refill_garbage() || (num_cleared == 0 && !self.add_incoming_garbage()) {
+            self.end("You lost!");
+            return;
+        }
+        if self.is_goal_reached() {
+            self.end(self.mode.completion_message().unwrap_or("Complete!"));
+            return;
+        }
+
+        if let Mode::Invisible(invisible) = &self.mode {
+            if invisible.flash_on_clear && num_cleared > 0 {
+                self.flash_until = self.frame + FLASH_FRAMES;
+            }
+        }
+
+        let timings = self.mode.timings(self.level);
+        self.spawn_delay = timings.are;
+        if num_cleared > 0 {
+            self.spawn_delay += timings.line_clear;
+        }
+        if self.spawn_delay == 0 {
+            self.spawn_next_piece();
+        }
+    }
+
+    // Counts the inputs spent on a placement beyond the fewest that could
+    // have put the piece there as finesse faults. In finesse mode, also
+    // returns a message saying how the placement went.
+    fn check_finesse(&mut self, placed: &PositionedTetromino) -> Option<String> {
+        let start = self.piece_start.take()?;
+        let trainer = matches!(self.mode, Mode::Finesse);
+        if !trainer && !self.counts_finesse {
+            return None;
+        }
+        let optimal = finesse::optimal_inputs(&start, placed, &self.settled_pieces)?;
+        let faults = self.piece_inputs.saturating_sub(finesse::count_inputs(&optimal));
+        self.stats.finesse_faults += faults;
+        if !trainer {
+            return None;
+        }
+        let target = self.target.take()?;
+        if *placed != target {
+            let path = finesse::optimal_inputs(&start, &target, &self.settled_pieces)?;
+            Some(format!("Missed! Try: {}", finesse::format_inputs(&path)))
+        } else if faults > 0 {
+            Some(format!("Fault! Best: {}", finesse::format_inputs(&optimal)))
+        } else {
+            Some(String::from("Perfect!"))
+        }
+    }
+
+    // Updates the combo and back-to-back chains and the stats for a placement,
+    // returning the lines of garbage it sends.
+    fn record_clear(&mut self, clear: &Clear) -> u32 {
+        if clear.lines == 0 {
+            self.combo = None;
+            return 0;
+        }
+        let combo = self.combo.map_or(0, |combo| combo + 1);
+        let attack = attack::attack(clear, combo, self.back_to_back);
+        self.combo = Some(combo);
+        self.back_to_back = clear.is_difficult();
+        self.stats.record_clear(clear, attack, combo);
+        if clear.t_spin {
+            self.debug_msg = String::from(match clear.lines {
+                1 => "T-SPIN SINGLE!",
+                2 => "T-SPIN DOUBLE!",
+                _ => "T-SPIN TRIPLE!",
+            });
+        }
+        attack
+    }
+
+    fn end(&mut self, msg: &str) {
+        self.active_piece = None;
+        self.debug_msg = String::from(msg);
+        self.finished = Some(self.frame);
+    }
+
+    // Tops the board back up to DIG_VISIBLE_ROWS garbage rows while the dig
+    // mode still has rows left to send. Returns false if this topped out.
+    fn refill_garbage(&mut self) -> bool {
+        let visible = self.garbage_rows_on_board();
+        if self.garbage_pending == 0 || visible >= DIG_VISIBLE_ROWS {
+            return true;
+        }
+        let count = (DIG_VISIBLE_ROWS - visible).min(self.garbage_pending);
+        self.garbage_pending -= count;
+        let rows = self.garbage.rows(count, &mut self.rng);
+        self.insert_rows_from_bottom(&rows)
+    }
+
+    pub fn set_garbage_rules(&mut self, rules: GarbageRules) {
+        self.garbage_rules = rules;
+    }
+
+    // Stops finesse faults being counted, except in finesse mode, where
+    // they're the point. Each placement checked costs a search, which adds
+    // up in headless bot games.
+    pub fn skip_finesse(&mut self) {
+        self.counts_finesse = false;
+    }
+
+    pub fn garbage_rules(&self) -> &GarbageRules {
+        &self.garbage_rules
+    }
+
+    // Queues up garbage sent by an opponent. Once the delay's passed, it's
+    // added to the board the next time a piece locks without clearing any
+    // lines.
+    pub fn receive_garbage(&mut self, lines: u32) {
+        if lines > 0 {
+            let ready_at = self.frame + self.garbage_rules.delay as u64;
+            self.incoming.push_back(Incoming { lines, ready_at });
+        }
+    }
+
+    // The lines of garbage sent since the last call, after any that went to
+    // cancelling incoming garbage.
+    pub fn take_attack(&mut self) -> u32 {
+        std::mem::take(&mut self.outgoing)
+    }
+
+    // Uses an attack to cancel incoming garbage, oldest first, whether or
+    // not it's ready to rise yet. Returns what's left of it to send on.
+    fn cancel_incoming(&mut self, mut attack: u32) -> u32 {
+        while attack > 0 {
+            let Some(incoming) = self.incoming.front_mut() else {
+                break;
+            };
+            let cancelled = attack.min(incoming.lines);
+            attack -= cancelled;
+            incoming.lines -= cancelled;
+            if incoming.lines == 0 {
+                self.incoming.pop_front();
+            }
+        }
+        attack
+    }
+
+    // Adds the incoming garbage that's waited long enough to the board.
+    // Returns false if this topped out.
+    fn add_incoming_garbage(&mut self) -> bool {
+        let mut rows = Vec::new();
+        while let Some(incoming) = self.incoming.front() {
+            if incoming.ready_at > self.frame {
+                break;
+            }
+            rows.extend(self.garbage_rules.rows(incoming.lines, &mut self.rng));
+            self.incoming.pop_front();
+        }
+        self.insert_rows_from_bottom(&rows)
+    }
+
+    // Pushes the given rows in under the stack, shifting everything else up.
+    // The first row ends up at the bottom. Returns false if any settled block
+    // was pushed off the top of the board.
+    pub fn insert_rows_from_bottom(&mut self, rows: &[Row]) -> bool {
+        let count = rows.len().min(HEIGHT as usize);
+        let overflowed = self.settled_pieces[..count]
+            .iter()
+            .any(|row| row.iter().any(|cell| *cell != Cell::Empty));
+        self.settled_pieces.rotate_left(count);
+        self.locked_at.rotate_left(count);
+        for (i, row) in rows[..count].iter().enumerate() {
+            self.settled_pieces[HEIGHT as usize - 1 - i] = *row;
+            self.locked_at[HEIGHT as usize - 1 - i] = [self.frame; WIDTH as usize];
+        }
+        !overflowed
+    }
+
+    fn garbage_rows_on_board(&self) -> u32 {
+        self.settled_pieces
+            .iter()
+            .filter(|row| row.contains(&Cell::Garbage))
+            .count() as u32
+    }
+
+    fn clear_full_rows(&mut self) -> u32 {
         let mut num_cleared = 0;
         for y in (0..HEIGHT).rev() {
             loop {
@@ -114,6 +667,27 @@ impl Game {
             }
             _ => self.debug_msg = String::from("You can do it!")
         };
+        self.add_lines(num_cleared);
+        num_cleared
+    }
+
+    fn add_lines(&mut self, num_cleared: u32) {
+        self.lines += num_cleared;
+        self.level = match &self.mode {
+            Mode::Marathon(marathon) => marathon.level_for_lines(self.lines),
+            Mode::Invisible(invisible) => invisible.level_for_lines(self.lines),
+            Mode::Dig(_) | Mode::Zen(_) | Mode::Finesse => self.level,
+            Mode::Master => (self.level + num_cleared).min(MASTER_MAX_LEVEL),
+        };
+    }
+
+    fn is_goal_reached(&self) -> bool {
+        match &self.mode {
+            Mode::Marathon(marathon) => marathon.is_complete(self.lines),
+            Mode::Dig(_) => self.garbage_pending == 0 && self.garbage_rows_on_board() == 0,
+            Mode::Master => self.level >= MASTER_MAX_LEVEL,
+            Mode::Zen(_) | Mode::Invisible(_) | Mode::Finesse => false,
+        }
     }
 
     fn shift_rows_down(&mut self, cleared_y: i32) {
@@ -121,6 +695,8 @@ impl Game {
             for x in 0..WIDTH {
                 self.settled_pieces[(y + 1) as usize][x as usize]
                     = self.settled_pieces[y as usize][x as usize];
+                self.locked_at[(y + 1) as usize][x as usize]
+                    = self.locked_at[y as usize][x as usize];
             }
         }
         for x in 0..WIDTH {
@@ -129,20 +705,27 @@ impl Game {
     }
 
     fn increase_score(&mut self, points: i32) {
-        let old_score = self.score;
-        self.score += points;
-        if self.score / 1000 > old_score / 1000 {
-            self.drop_interval = (self.drop_interval * 8) / 10;
-        }
+        self.score += points * self.mode.score_multiplier(self.level) as i32;
     }
 
     pub fn render_board(&self) -> Board {
-        let mut board = self.settled_pieces.clone();
+        let mut board = self.settled_pieces;
+        if let Mode::Invisible(invisible) = &self.mode {
+            if !self.is_over() && self.frame >= self.flash_until {
+                let visible_frames = (invisible.fade_seconds * FRAMES_PER_SECOND) as u64;
+                self.hide_settled_cells(&mut board, visible_frames);
+            }
+        }
+        if let Some(ref target) = self.target {
+            for (y, x) in target.get_coords() {
+                if board[y as usize][x as usize] == Cell::Empty {
+                    board[y as usize][x as usize] = Cell::Target;
+                }
+            }
+        }
         if let Some(ref active_piece) = self.active_piece {
             let mut preview_piece = active_piece.clone();
-            while preview_piece.can_move_down(&self.settled_pieces) {
-                preview_piece.move_down(&self.settled_pieces);
-            }
+            preview_piece.drop_to_floor(&self.settled_pieces);
             for (y, x) in preview_piece.get_coords() {
                 board[y as usize][x as usize] = preview_piece.get_ghost_cell_type();
             }
@@ -154,6 +737,33 @@ impl Game {
         board
     }
 
+    // Blanks out cells locked more than visible_frames ago, drawing them as
+    // outlines for a short while first so they appear to fade.
+    fn hide_settled_cells(&self, board: &mut Board, visible_frames: u64) {
+        for (y, row) in board.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                let age = self.frame - self.locked_at[y][x];
+                if age >= visible_frames + FADE_FRAMES || (visible_frames == 0 && age > 0) {
+                    *cell = Cell::Empty;
+                } else if age >= visible_frames {
+                    *cell = cell.faded();
+                }
+            }
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.settled_pieces
+    }
+
+    pub fn active_piece(&self) -> Option<&PositionedTetromino> {
+        self.active_piece.as_ref()
+    }
+
+    pub fn next_piece(&self) -> &Tetromino {
+        &self.next_piece
+    }
+
     pub fn render_next_piece(&self) -> [[Cell; 4]; 2] {
         let mut grid = [[Cell::Empty; 4]; 2];
         for (y, x) in self.next_piece.get_preview_coords() {
@@ -167,10 +777,127 @@ impl Game {
     }
 
     pub fn render_score(&self) -> i32 { self.score }
+
+    pub fn render_level(&self) -> u32 { self.level }
+
+    pub fn render_lines(&self) -> u32 { self.lines }
+
+    pub fn render_pieces(&self) -> u32 { self.pieces }
+
+    pub fn frame(&self) -> u64 { self.frame }
+
+    pub fn render_time(&self) -> Duration {
+        let frames = self.finished.unwrap_or(self.frame);
+        Duration::from_secs_f64(frames as f64 / FRAMES_PER_SECOND as f64)
+    }
+
+    // The shortest key sequence from the active piece's current position to
+    // the finesse target, or None if there's no target to reach.
+    pub fn render_target_inputs(&self) -> Option<&str> {
+        self.target_inputs.as_ref().map(|(_, inputs)| inputs.as_str())
+    }
+
+    pub fn render_garbage_remaining(&self) -> u32 {
+        self.garbage_pending + self.garbage_rows_on_board()
+    }
+
+    // Lines of incoming garbage that will rise with the next piece that
+    // doesn't clear, and lines still waiting out the delay.
+    pub fn render_incoming_garbage(&self) -> (u32, u32) {
+        self.incoming.iter().fold((0, 0), |(ready, waiting), incoming| {
+            if incoming.ready_at <= self.frame {
+                (ready + incoming.lines, waiting)
+            } else {
+                (ready, waiting + incoming.lines)
+            }
+        })
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn pieces_per_second(&self) -> f64 {
+        let seconds = self.render_time().as_secs_f64();
+        if seconds > 0.0 { self.pieces as f64 / seconds } else { 0.0 }
+    }
+
+    pub fn attack_per_minute(&self) -> f64 {
+        let minutes = self.render_time().as_secs_f64() / 60.0;
+        if minutes > 0.0 { self.stats.attack as f64 / minutes } else { 0.0 }
+    }
+
+    pub fn keys_per_piece(&self) -> f64 {
+        if self.pieces > 0 { self.stats.keys as f64 / self.pieces as f64 } else { 0.0 }
+    }
+
+    // Line clears in a row so far, counting the first.
+    pub fn combo(&self) -> u32 {
+        self.combo.map_or(0, |combo| combo + 1)
+    }
+
+    pub fn back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn randomizer(&self) -> Randomizer {
+        self.dealer.randomizer()
+    }
+
+    pub fn setup(&self) -> Option<&Setup> {
+        self.setup.as_ref()
+    }
+
+    // The position as it stands, to pick up from somewhere else.
+    pub fn current_setup(&self) -> Setup {
+        Setup {
+            board: self.settled_pieces,
+            current: self.active_piece.as_ref().map(|piece| piece.tetromino().unrotated()),
+            queue: vec![self.next_piece.clone()],
+            hold: None,
+        }
+    }
+
+    pub fn mode(&self) -> &Mode {
+        &self.mode
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.finished.is_some()
+    }
+
+    // The frame the game ended on.
+    pub fn finished_at(&self) -> Option<u64> {
+        self.finished
+    }
+
+    // A hash of the board, pieces and progress, for checking that two copies
+    // of a game are still in step. It's FNV-1a over them as JSON, which comes
+    // out the same whichever build of the game made it.
+    pub fn state_hash(&self) -> u64 {
+        let state = (
+            &self.settled_pieces,
+            &self.active_piece,
+            &self.next_piece,
+            (self.score, self.lines, self.pieces, self.frame),
+        );
+        let json = serde_json::to_vec(&state).expect("the game's state is always valid JSON");
+        json.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+    }
+
+    // Whether the game ended by reaching its goal, rather than topping out.
+    pub fn is_complete(&self) -> bool {
+        self.is_over() && self.is_goal_reached()
+    }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Cell {
+    #[default]
     Empty,
     IBlock,
     JBlock,
@@ -179,6 +906,7 @@ pub enum Cell {
     SBlock,
     TBlock,
     ZBlock,
+    Garbage,
     IGhostBlock,
     JGhostBlock,
     LGhostBlock,
@@ -186,16 +914,36 @@ pub enum Cell {
     SGhostBlock,
     TGhostBlock,
     ZGhostBlock,
+    Target,
+}
+
+impl Cell {
+    // The outline version of a block, as used for the ghost piece.
+    pub fn faded(self) -> Cell {
+        match self {
+            Cell::IBlock => Cell::IGhostBlock,
+            Cell::JBlock => Cell::JGhostBlock,
+            Cell::LBlock => Cell::LGhostBlock,
+            Cell::OBlock => Cell::OGhostBlock,
+            Cell::SBlock => Cell::SGhostBlock,
+            Cell::TBlock => Cell::TGhostBlock,
+            Cell::ZBlock => Cell::ZGhostBlock,
+            other => other,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Command {
     Left,
     Right,
     Drop,
+    HardDrop,
     Up,
     CounterClockwise,
     Clockwise,
     ChangePiece,
+    Undo,
+    Redo,
     NoOp,
 }
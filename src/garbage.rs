@@ -0,0 +1,96 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Cell, Row, WIDTH};
+
+// Generates garbage rows with a single hole each. Messiness is the chance
+// (from 0.0 to 1.0) that the hole moves to a different column between one row
+// and the next: 0.0 gives a clean well, 1.0 gives classic cheese.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GarbageGenerator {
+    messiness: f64,
+    hole: i32,
+}
+
+impl GarbageGenerator {
+    pub fn new(messiness: f64, rng: &mut impl Rng) -> Self {
+        // NaN would make gen_bool panic, so it's taken as a clean well.
+        let messiness = if messiness.is_nan() { 0.0 } else { messiness.clamp(0.0, 1.0) };
+        Self {
+            messiness,
+            hole: rng.gen_range(0..WIDTH),
+        }
+    }
+
+    pub fn next_row(&mut self, rng: &mut impl Rng) -> Row {
+        if rng.gen_bool(self.messiness) {
+            let offset = rng.gen_range(1..WIDTH);
+            self.hole = (self.hole + offset) % WIDTH;
+        }
+        let mut row = [Cell::Garbage; WIDTH as usize];
+        row[self.hole as usize] = Cell::Empty;
+        row
+    }
+
+    pub fn rows(&mut self, count: u32, rng: &mut impl Rng) -> Vec<Row> {
+        (0..count).map(|_| self.next_row(rng)).collect()
+    }
+}
+
+// Where the holes go in garbage sent by an opponent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum HolePolicy {
+    // Every row sent by one attack has its hole in the same column.
+    #[default]
+    SameColumn,
+    // Each row has its hole somewhere different.
+    RandomPerRow,
+}
+
+impl HolePolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HolePolicy::SameColumn => "same",
+            HolePolicy::RandomPerRow => "random",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "same" => Some(HolePolicy::SameColumn),
+            "random" => Some(HolePolicy::RandomPerRow),
+            _ => None,
+        }
+    }
+
+    fn messiness(&self) -> f64 {
+        match self {
+            HolePolicy::SameColumn => 0.0,
+            HolePolicy::RandomPerRow => 1.0,
+        }
+    }
+}
+
+// How garbage sent by an opponent reaches the board. It waits in a queue for
+// `delay` frames, during which it can be cancelled by the receiver's own
+// attacks, then rises the next time they lock a piece without clearing.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GarbageRules {
+    pub delay: u32,
+    pub holes: HolePolicy,
+}
+
+pub const DEFAULT_GARBAGE_DELAY: u32 = 20;
+
+impl Default for GarbageRules {
+    fn default() -> Self {
+        Self { delay: DEFAULT_GARBAGE_DELAY, holes: HolePolicy::default() }
+    }
+}
+
+impl GarbageRules {
+    // The rows for one attack of the given size.
+    pub fn rows(&self, lines: u32, rng: &mut impl Rng) -> Vec<Row> {
+        GarbageGenerator::new(self.holes.messiness(), rng).rows(lines, rng)
+    }
+}
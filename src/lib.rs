@@ -5,17 +5,54 @@ use crossterm::event;
 use crossterm::event::{Event, KeyCode};
 use ratatui::{Frame, Terminal};
 use ratatui::backend::Backend;
-use ratatui::layout::{Alignment, Constraint, Direction, Layout};
+use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, BorderType, Paragraph};
+use ratatui::widgets::{Block, Borders, BorderType, Clear, Paragraph};
 use ratatui::widgets::block::Title;
 
+use crate::bot::{Bot, Planner};
 use crate::game::{Board, Cell, Command, Game, HEIGHT, NextPiece, WIDTH};
+use crate::mode::Mode;
+use crate::net::{NetMatch, Spectating};
+use crate::replay::{Replay, VersusReplay};
+use crate::scores::{HighScores, MAX_NAME_LEN};
 use crate::tetromino::PositionedTetromino;
+use crate::versus::{PLAYERS, Versus};
 
-mod tetromino;
+pub mod attack;
+pub mod bot;
+pub mod editor;
+pub mod env;
+pub mod finesse;
+pub mod fumen;
 pub mod game;
+pub mod garbage;
+pub mod menu;
+pub mod mode;
+pub mod movegen;
+pub mod net;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod randomizer;
+pub mod replay;
+pub mod save;
+pub mod scores;
+pub mod sim;
+pub mod stats;
+pub mod storage;
+pub mod tbp;
+pub mod tetromino;
+pub mod tournament;
+pub mod tune;
+pub mod versus;
+
+const SIDE_BAR_WIDTH: u16 = 18;
+const METER_WIDTH: u16 = 1;
+const STAT_LABEL_WIDTH: usize = 7;
+
+const REPLAY_SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 2;
 
 fn render_cell(cell: &Cell) -> Span<'static> {
     match cell {
@@ -26,6 +63,7 @@ fn render_cell(cell: &Cell) -> Span<'static> {
         Cell::SBlock => Span::styled("██", Style::new().fg(Color::Rgb(72, 208, 72))),
         Cell::TBlock => Span::styled("██", Style::new().fg(Color::Rgb(173, 77, 156))),
         Cell::ZBlock => Span::styled("██", Style::new().fg(Color::Rgb(239, 32, 41))),
+        Cell::Garbage => Span::styled("██", Style::new().fg(Color::Rgb(130, 130, 130))),
         Cell::IGhostBlock => Span::styled("[]", Style::new().fg(Color::Rgb(49, 199, 239))),
         Cell::JGhostBlock => Span::styled("[]", Style::new().fg(Color::Rgb(90, 101, 173))),
         Cell::LGhostBlock => Span::styled("[]", Style::new().fg(Color::Rgb(239, 121, 33))),
@@ -33,6 +71,7 @@ fn render_cell(cell: &Cell) -> Span<'static> {
         Cell::SGhostBlock => Span::styled("[]", Style::new().fg(Color::Rgb(72, 208, 72))),
         Cell::TGhostBlock => Span::styled("[]", Style::new().fg(Color::Rgb(173, 77, 156))),
         Cell::ZGhostBlock => Span::styled("[]", Style::new().fg(Color::Rgb(239, 32, 41))),
+        Cell::Target => Span::styled("░░", Style::new().fg(Color::Rgb(200, 200, 200))),
         Cell::Empty => Span::raw("  "),
     }
 }
@@ -57,7 +96,79 @@ fn render_next_piece_to_text(piece: NextPiece) -> Vec<Line<'static, >> {
     ).collect()
 }
 
+fn format_time(time: Duration) -> String {
+    let centis = time.as_millis() / 10;
+    format!("{}:{:02}.{:02}", centis / 6000, (centis / 100) % 60, centis % 100)
+}
+
+fn render_stats(game: &Game) -> Vec<(&'static str, String)> {
+    let mut stats = Vec::new();
+    match game.mode() {
+        Mode::Marathon(_) | Mode::Master | Mode::Invisible(_) => {
+            stats.push(("Level", format!("{}", game.render_level())));
+            stats.push(("Lines", format!("{}", game.render_lines())));
+        }
+        Mode::Dig(_) => {
+            stats.push(("Garbage", format!("{}", game.render_garbage_remaining())));
+            stats.push(("Pieces", format!("{}", game.render_pieces())));
+        }
+        Mode::Zen(_) => {
+            stats.push(("Lines", format!("{}", game.render_lines())));
+            stats.push(("Pieces", format!("{}", game.render_pieces())));
+        }
+        Mode::Finesse => {
+            stats.push(("Target", game.render_target_inputs().unwrap_or_default().to_string()));
+            stats.push(("Pieces", format!("{}", game.render_pieces())));
+        }
+    }
+    let totals = game.stats();
+    stats.extend([
+        ("Time", format_time(game.render_time())),
+        ("PPS", format!("{:.2}", game.pieces_per_second())),
+        ("APM", format!("{:.1}", game.attack_per_minute())),
+        ("KPP", format!("{:.2}", game.keys_per_piece())),
+        ("Faults", format!("{}", totals.finesse_faults)),
+        ("Single", format!("{}", totals.singles)),
+        ("Double", format!("{}", totals.doubles)),
+        ("Triple", format!("{}", totals.triples)),
+        ("Tetris", format!("{}", totals.tetrises)),
+        ("T-spin", format!("{}", totals.t_spins)),
+        ("Combo", format!("{}", totals.max_combo)),
+    ]);
+    stats
+}
+
+fn render_stats_to_text(stats: Vec<(&'static str, String)>) -> Vec<Line<'static>> {
+    let value_width = SIDE_BAR_WIDTH as usize - 2 - STAT_LABEL_WIDTH;
+    stats.into_iter()
+        .map(|(label, value)| {
+            Line::from(format!("{label:<STAT_LABEL_WIDTH$}{value:>value_width$}"))
+        })
+        .collect()
+}
+
+// A column alongside the board rows, filled from the bottom with one block per
+// line of incoming garbage: bright for lines that will rise on the next lock,
+// dim for ones still waiting out their delay.
+fn render_garbage_meter((ready, waiting): (u32, u32)) -> Vec<Line<'static>> {
+    let ready = ready.min(HEIGHT as u32);
+    let waiting = waiting.min(HEIGHT as u32 - ready);
+    let empty = HEIGHT as u32 - ready - waiting;
+    let block = |color| Line::from(Span::styled("█", Style::new().fg(color)));
+    std::iter::once(Line::from(""))
+        .chain((0..empty).map(|_| Line::from("")))
+        .chain((0..waiting).map(|_| block(Color::Rgb(120, 16, 20))))
+        .chain((0..ready).map(|_| block(Color::Rgb(239, 32, 41))))
+        .collect()
+}
+
 fn render<B: Backend>(f: &mut Frame<B>, game: &Game) {
+    render_game(f, game, f.size(), "");
+}
+
+// Draws the board, side bar and message for a game within the given area,
+// with the title over the board.
+fn render_game<B: Backend>(f: &mut Frame<B>, game: &Game, area: Rect, title: &str) {
     let board_width = (WIDTH * 2) as u16;
     let board_width_with_border = board_width + 2;
     let board_height = HEIGHT as u16;
@@ -68,7 +179,7 @@ fn render<B: Backend>(f: &mut Frame<B>, game: &Game) {
             Constraint::Length(board_height_with_border),
             Constraint::Length(1)
         ].as_ref())
-        .split(f.size());
+        .split(area);
 
     let msg_layout = Layout::default()
         .direction(Direction::Horizontal)
@@ -87,25 +198,33 @@ fn render<B: Backend>(f: &mut Frame<B>, game: &Game) {
         .direction(Direction::Horizontal)
         .constraints([
             Constraint::Length(board_width_with_border),
-            Constraint::Length(10),
+            Constraint::Length(METER_WIDTH),
+            Constraint::Length(SIDE_BAR_WIDTH),
             Constraint::Min(0),
         ].as_ref())
         .split(main_layout[0]);
 
     let board = Paragraph::new(render_board_to_text(game.render_board()))
         .block(Block::default()
+            .title(title.to_string())
+            .title_alignment(Alignment::Center)
             .borders(Borders::ALL)
             .border_type(BorderType::Double));
     f.render_widget(board, game_area[0]);
 
+    let meter = Paragraph::new(render_garbage_meter(game.render_incoming_garbage()));
+    f.render_widget(meter, game_area[1]);
+
+    let stats = render_stats(game);
     let side_bar = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(4),
             Constraint::Length(3),
+            Constraint::Length(stats.len() as u16 + 2),
             Constraint::Min(0),
         ].as_ref())
-        .split(game_area[1]);
+        .split(game_area[2]);
 
     let next_piece = Paragraph::new(
         render_next_piece_to_text(game.render_next_piece())
@@ -124,35 +243,298 @@ fn render<B: Backend>(f: &mut Frame<B>, game: &Game) {
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded));
     f.render_widget(score, side_bar[1]);
+
+    let stats_panel = Paragraph::new(render_stats_to_text(stats))
+        .block(Block::default()
+            .title("Stats")
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded));
+    f.render_widget(stats_panel, side_bar[2]);
+}
+
+// Titles for the two boards, marking the one played here if there is one.
+fn player_titles(you: Option<usize>) -> [String; PLAYERS] {
+    std::array::from_fn(|player| {
+        if you == Some(player) {
+            format!("Player {} (you)", player + 1)
+        } else {
+            format!("Player {}", player + 1)
+        }
+    })
+}
+
+// Draws both games of a match side by side, with the status underneath.
+fn render_versus<B: Backend>(
+    f: &mut Frame<B>,
+    versus: &Versus,
+    titles: &[String; PLAYERS],
+    status: &str,
+) {
+    let player_width = (WIDTH * 2) as u16 + 2 + METER_WIDTH + SIDE_BAR_WIDTH;
+    let players = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(player_width + 2),
+            Constraint::Length(player_width),
+            Constraint::Min(0),
+        ].as_ref())
+        .split(f.size());
+    for (player, game) in versus.games().iter().enumerate() {
+        render_game(f, game, players[player], &titles[player]);
+    }
+    let area = f.size();
+    let status_area = Rect::new(area.x + 1, area.y + HEIGHT as u16 + 3, area.width, 1)
+        .intersection(area);
+    f.render_widget(Paragraph::new(status.to_string()), status_area);
+
+    if versus.is_over() {
+        let result = match versus.winner() {
+            Some(player) => format!("Player {} wins!", player + 1),
+            None => String::from("Draw!"),
+        };
+        let area = Rect::new(player_width - 9, HEIGHT as u16 / 2 - 1, 22, 4)
+            .intersection(f.size());
+        let banner = Paragraph::new(vec![Line::from(result), Line::from("Esc to quit")])
+            .alignment(Alignment::Center)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded));
+        f.render_widget(Clear, area);
+        f.render_widget(banner, area);
+    }
+}
+
+fn render_replay<B: Backend>(f: &mut Frame<B>, game: &Game, status: &str) {
+    render(f, game);
+    let area = f.size();
+    let status_area = Rect::new(area.x + 1, area.y + HEIGHT as u16 + 3, area.width, 1)
+        .intersection(area);
+    f.render_widget(Paragraph::new(status.to_string()), status_area);
+}
+
+fn render_initials_prompt<B: Backend>(f: &mut Frame<B>, game: &Game, name: &str) {
+    render(f, game);
+    let area = Rect::new(1, HEIGHT as u16 / 2 - 1, (WIDTH * 2) as u16, 4)
+        .intersection(f.size());
+    let prompt = Paragraph::new(vec![
+        Line::from("Enter your initials"),
+        Line::from(format!("{name:_<MAX_NAME_LEN$}")),
+    ]).alignment(Alignment::Center)
+        .block(Block::default()
+            .title("High score!")
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded));
+    f.render_widget(Clear, area);
+    f.render_widget(prompt, area);
 }
 
+// Asks for the player's initials. Returns None if they skip it with Esc.
+fn prompt_initials<B: Backend>(
+    terminal: &mut Terminal<B>,
+    game: &Game,
+) -> io::Result<Option<String>> {
+    // Drop any keys still queued up from play, so they aren't taken as initials.
+    while event::poll(Duration::from_secs(0))? {
+        event::read()?;
+    }
+    let mut name = String::new();
+    loop {
+        terminal.draw(|f| render_initials_prompt(f, game, &name))?;
+        if let Event::Key(key) = event::read()? {
+            match key.code {
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Enter if !name.is_empty() => return Ok(Some(name)),
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Char(c) if c.is_ascii_alphanumeric() && name.len() < MAX_NAME_LEN => {
+                    name.push(c.to_ascii_uppercase());
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+// The command a key stands for when playing alone.
+fn key_command(code: KeyCode) -> Command {
+    match code {
+        KeyCode::Char('h') => Command::Left,
+        KeyCode::Char('n') => Command::Right,
+        KeyCode::Char('t') => Command::Drop,
+        KeyCode::Char(' ') => Command::HardDrop,
+        KeyCode::Char('c') => Command::Up,
+        KeyCode::Char(';') => Command::CounterClockwise,
+        KeyCode::Char('j') => Command::Clockwise,
+        KeyCode::Char(',') => Command::ChangePiece,
+        KeyCode::Char('u') => Command::Undo,
+        KeyCode::Char('r') => Command::Redo,
+        _ => Command::NoOp,
+    }
+}
+
+// The command a key stands for against an opponent: the same as playing
+// alone, except for the keys that lift or swap the piece, or undo and redo,
+// which would let a player stall or pick their pieces.
+fn competitive_key_command(code: KeyCode) -> Command {
+    match key_command(code) {
+        Command::Up | Command::ChangePiece | Command::Undo | Command::Redo => Command::NoOp,
+        command => command,
+    }
+}
+
+// Runs the game until the player quits, recording every frame played before
+// the game ended into the replay. If the game ends with a high score, the
+// player is asked for their initials and it is added to the table.
 pub fn run_game<B: Backend>(
     terminal: &mut Terminal<B>,
-    mut game: Game,
+    game: &mut Game,
+    replay: &mut Replay,
+    scores: &mut HighScores,
     tick_rate: Duration,
 ) -> io::Result<()> {
     let mut last_tick = Instant::now();
     let mut command = Command::NoOp;
     loop {
-        terminal.draw(|f| render(f, &game))?;
+        terminal.draw(|f| render(f, game))?;
+
+        let timeout = tick_rate
+            .checked_sub(last_tick.elapsed())
+            .unwrap_or_else(|| Duration::from_secs(0));
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Char('\'')) {
+                    return Ok(());
+                }
+                let new_command = key_command(key.code);
+                if new_command != Command::NoOp {
+                    command = new_command;
+                }
+            }
+        }
+
+        if last_tick.elapsed() >= tick_rate {
+            if !game.is_over() {
+                replay.record(command);
+                game.tick(command);
+                if scores.qualifies(game) {
+                    if let Some(name) = prompt_initials(terminal, game)? {
+                        scores.insert(game, &name);
+                    }
+                }
+            }
+            last_tick = Instant::now();
+            command = Command::NoOp;
+        }
+    }
+}
+
+// Lets the bot play the game while the player watches, until they quit.
+pub fn run_bot<B: Backend, P: Planner>(
+    terminal: &mut Terminal<B>,
+    game: &mut Game,
+    bot: &mut Bot<P>,
+    tick_rate: Duration,
+) -> io::Result<()> {
+    let mut last_tick = Instant::now();
+    loop {
+        terminal.draw(|f| render(f, game))?;
+
+        let timeout = tick_rate
+            .checked_sub(last_tick.elapsed())
+            .unwrap_or_else(|| Duration::from_secs(0));
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Char('\'')) {
+                    return Ok(());
+                }
+            }
+        }
+
+        if last_tick.elapsed() >= tick_rate {
+            let command = bot.next_command(game)?;
+            game.tick(command);
+            last_tick = Instant::now();
+        }
+    }
+}
+
+// Plays a two-player match on one keyboard until the players quit with Esc.
+// Player one uses a and e to move, o to soft drop, ',' and '.' to rotate and
+// space to hard drop. Player two uses the arrow keys, with up to rotate
+// clockwise, '/' to rotate counter-clockwise and Enter to hard drop.
+pub fn run_versus<B: Backend>(
+    terminal: &mut Terminal<B>,
+    versus: &mut Versus,
+    tick_rate: Duration,
+) -> io::Result<()> {
+    let mut last_tick = Instant::now();
+    let mut commands = [Command::NoOp; PLAYERS];
+    loop {
+        terminal.draw(|f| render_versus(f, versus, &player_titles(None), ""))?;
 
         let timeout = tick_rate
             .checked_sub(last_tick.elapsed())
             .unwrap_or_else(|| Duration::from_secs(0));
         if event::poll(timeout)? {
             if let Event::Key(key) = event::read()? {
-                let new_command = match key.code {
+                let (player, command) = match key.code {
                     KeyCode::Esc => return Ok(()),
-                    KeyCode::Char('\'') => return Ok(()),
-                    KeyCode::Char('h') => Command::Left,
-                    KeyCode::Char('n') => Command::Right,
-                    KeyCode::Char('t') => Command::Drop,
-                    KeyCode::Char('c') => Command::Up,
-                    KeyCode::Char(';') => Command::CounterClockwise,
-                    KeyCode::Char('j') => Command::Clockwise,
-                    KeyCode::Char(',') => Command::ChangePiece,
-                    _ => Command::NoOp,
+                    KeyCode::Char('a') => (0, Command::Left),
+                    KeyCode::Char('e') => (0, Command::Right),
+                    KeyCode::Char('o') => (0, Command::Drop),
+                    KeyCode::Char(' ') => (0, Command::HardDrop),
+                    KeyCode::Char(',') => (0, Command::CounterClockwise),
+                    KeyCode::Char('.') => (0, Command::Clockwise),
+                    KeyCode::Left => (1, Command::Left),
+                    KeyCode::Right => (1, Command::Right),
+                    KeyCode::Down => (1, Command::Drop),
+                    KeyCode::Enter => (1, Command::HardDrop),
+                    KeyCode::Char('/') => (1, Command::CounterClockwise),
+                    KeyCode::Up => (1, Command::Clockwise),
+                    _ => (0, Command::NoOp),
                 };
+                if command != Command::NoOp {
+                    commands[player] = command;
+                }
+            }
+        }
+
+        if last_tick.elapsed() >= tick_rate {
+            versus.tick(commands);
+            last_tick = Instant::now();
+            commands = [Command::NoOp; PLAYERS];
+        }
+    }
+}
+
+// Plays a match against the bot, which takes the right-hand board, until the
+// player quits. Keys are the same as playing alone, less those that would
+// give the player an unfair edge.
+pub fn run_versus_bot<B: Backend, P: Planner>(
+    terminal: &mut Terminal<B>,
+    versus: &mut Versus,
+    bot: &mut Bot<P>,
+    bot_name: &str,
+    tick_rate: Duration,
+) -> io::Result<()> {
+    let mut last_tick = Instant::now();
+    let mut command = Command::NoOp;
+    let titles = [String::from("You"), bot_name.to_string()];
+    loop {
+        terminal.draw(|f| render_versus(f, versus, &titles, ""))?;
+
+        let timeout = tick_rate
+            .checked_sub(last_tick.elapsed())
+            .unwrap_or_else(|| Duration::from_secs(0));
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Char('\'')) {
+                    return Ok(());
+                }
+                let new_command = competitive_key_command(key.code);
                 if new_command != Command::NoOp {
                     command = new_command;
                 }
@@ -160,9 +542,160 @@ pub fn run_game<B: Backend>(
         }
 
         if last_tick.elapsed() >= tick_rate {
-            game.tick(command);
+            let bot_command = bot.next_command(versus.game(1))?;
+            versus.tick([command, bot_command]);
             last_tick = Instant::now();
             command = Command::NoOp;
         }
     }
 }
+
+// Plays a networked match until the player quits, with the bot playing if
+// one is given. Keys are the same as against the bot.
+pub fn run_net<B: Backend, P: Planner>(
+    terminal: &mut Terminal<B>,
+    net: &mut NetMatch,
+    mut bot: Option<&mut Bot<P>>,
+    tick_rate: Duration,
+) -> io::Result<()> {
+    let mut last_tick = Instant::now();
+    let mut command = Command::NoOp;
+    let titles = player_titles(Some(net.player()));
+    loop {
+        net.poll()?;
+        terminal.draw(|f| render_versus(f, net.versus(), &titles, &net.status()))?;
+
+        let timeout = tick_rate
+            .checked_sub(last_tick.elapsed())
+            .unwrap_or_else(|| Duration::from_secs(0));
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Char('\'')) {
+                    return Ok(());
+                }
+                let new_command = competitive_key_command(key.code);
+                if new_command != Command::NoOp {
+                    command = new_command;
+                }
+            }
+        }
+
+        if last_tick.elapsed() >= tick_rate {
+            if let Some(bot) = bot.as_mut() {
+                command = bot.next_command(net.game())?;
+            }
+            net.tick(command);
+            last_tick = Instant::now();
+            command = Command::NoOp;
+        }
+    }
+}
+
+// Shows a hosted match live until the spectator quits.
+pub fn run_watch<B: Backend>(
+    terminal: &mut Terminal<B>,
+    spectating: &mut Spectating,
+    tick_rate: Duration,
+) -> io::Result<()> {
+    let titles = player_titles(None);
+    loop {
+        spectating.poll()?;
+        terminal.draw(|f| render_versus(f, spectating.versus(), &titles, &spectating.status()))?;
+        if event::poll(tick_rate)? {
+            if let Event::Key(key) = event::read()? {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Char('\'')) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+// Plays a replay back. Space pauses, '.' steps a single frame while paused,
+// and '+' and '-' change the playback speed.
+pub fn run_replay<B: Backend>(
+    terminal: &mut Terminal<B>,
+    replay: &Replay,
+    tick_rate: Duration,
+) -> io::Result<()> {
+    let mut commands = replay.commands();
+    play_back(
+        terminal,
+        replay.new_game(),
+        replay.frames(),
+        tick_rate,
+        |f, game, status| render_replay(f, game, status),
+        |game| commands.next().map(|command| game.tick(command)).is_some(),
+    )
+}
+
+pub fn run_versus_replay<B: Backend>(
+    terminal: &mut Terminal<B>,
+    replay: &VersusReplay,
+    tick_rate: Duration,
+) -> io::Result<()> {
+    let mut commands = replay.commands();
+    play_back(
+        terminal,
+        replay.new_versus(),
+        replay.frames(),
+        tick_rate,
+        |f, versus, status| render_versus(f, versus, replay.names(), status),
+        |versus| commands.next().map(|commands| versus.tick(commands)).is_some(),
+    )
+}
+
+// Plays a replay back a frame at a time, at a speed that can be changed,
+// pausing and stepping on request. `advance` runs the next frame on `state`,
+// returning false once there are none left.
+fn play_back<B: Backend, S>(
+    terminal: &mut Terminal<B>,
+    mut state: S,
+    total_frames: u32,
+    tick_rate: Duration,
+    draw: impl Fn(&mut Frame<B>, &S, &str),
+    mut advance: impl FnMut(&mut S) -> bool,
+) -> io::Result<()> {
+    let mut frame = 0;
+    let mut speed = NORMAL_SPEED;
+    let mut paused = false;
+    let mut last_tick = Instant::now();
+    loop {
+        let status = format!(
+            "Replay {}x  frame {}/{}{}",
+            REPLAY_SPEEDS[speed],
+            frame,
+            total_frames,
+            if paused { "  (paused)" } else { "" },
+        );
+        terminal.draw(|f| draw(f, &state, &status))?;
+
+        let frame_time = tick_rate.div_f64(REPLAY_SPEEDS[speed]);
+        let timeout = frame_time
+            .checked_sub(last_tick.elapsed())
+            .unwrap_or_else(|| Duration::from_secs(0));
+        let mut step = false;
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                match key.code {
+                    KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('\'') => return Ok(()),
+                    KeyCode::Char(' ') => paused = !paused,
+                    KeyCode::Char('.') => step = paused,
+                    KeyCode::Char('+') | KeyCode::Char('=') => {
+                        speed = (speed + 1).min(REPLAY_SPEEDS.len() - 1);
+                    }
+                    KeyCode::Char('-') => speed = speed.saturating_sub(1),
+                    _ => (),
+                }
+            }
+        }
+
+        if step || (!paused && last_tick.elapsed() >= frame_time) {
+            if advance(&mut state) {
+                frame += 1;
+            }
+            last_tick = Instant::now();
+        }
+    }
+}
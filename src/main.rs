@@ -1,4 +1,4 @@
-use std::{io, time::Duration};
+use std::{fs, io, path::PathBuf, time::Duration};
 
 use crossterm::{
     event::{
@@ -14,13 +14,444 @@ use crossterm::{
     },
 };
 use ratatui::{
-    backend::CrosstermBackend,
+    backend::{Backend, CrosstermBackend},
     Terminal,
 };
 
-use tetris::game::Game;
+use tetris::bot::{Bot, Difficulty, Heuristic, Planner, Weights};
+use tetris::editor::{EditorChoice, run_editor};
+use tetris::fumen::{self, Page};
+use tetris::game::{Game, Setup};
+use tetris::garbage::{GarbageRules, HolePolicy};
+use tetris::menu::{MenuChoice, run_menu};
+use tetris::net::{self, NetMatch};
+use tetris::replay::{Recording, Replay};
+use tetris::randomizer::Randomizer;
+use tetris::save::SavedGame;
+use tetris::storage;
+use tetris::tbp::{self, TbpClient};
+use tetris::tournament::{self, Entrant, Format, Ratings, TournamentConfig};
+use tetris::tune::{TuneConfig, Tuner};
+use tetris::versus::Versus;
+use tetris::scores::HighScores;
+use tetris::mode::{
+    Dig, FRAMES_PER_SECOND, Invisible, Marathon, MAX_START_LEVEL, MIN_START_LEVEL, Mode, Zen,
+};
+
+const USAGE: &str = "\
+Usage: tetris [options]
+       tetris sim [options]
+       tetris tune [options]
+       tetris tournament [options]
+       tetris fumen [options]
+  --mode <name>        marathon (default), dig, master, zen, invisible
+                       or finesse
+  --level <1-15>       marathon start level
+  --lines <count>      marathon line goal, or dig garbage rows
+  --endless            marathon without a line goal
+  --messiness <0-1>    dig garbage messiness
+  --gravity            enable gravity in zen
+  --fade <seconds>     invisible: how long locked blocks stay visible
+  --flash              invisible: show the stack briefly on line clears
+  --versus             two players on one keyboard, sending each other
+                       garbage
+  --host <port>        host a versus match over the network
+  --join <address>     join a match hosted at host:port
+  --watch <address>    watch a match hosted at host:port
+  --garbage-delay <frames> versus: how long sent garbage waits before it
+                       can rise (default 20)
+  --garbage-holes <name> versus: same (default) for one hole column per
+                       attack, or random for a new column every row
+  --bot                watch the computer play
+  --lookahead          bot: plan around the next piece too
+  --pps <rate>         bot: place at most this many pieces a second
+  --difficulty <name>  bot: easy, medium, hard or expert, setting both
+                       --pps and --lookahead. Versus with --bot plays
+                       against the bot, at medium unless told otherwise
+  --tbp                run the bot as a Tetris Bot Protocol bot on stdin/stdout
+  --tbp-bot <command>  watch an external TBP bot play. The command is split
+                       on spaces, without a shell or quoting
+  --weights <file>     bot: use weights saved by tune
+  --replay <file>      play back a recorded game
+  --fumen <data>       start from a fumen page, given as data or a URL
+  --fumen-page <n>     which page of the fumen to start from (default 1)
+  --resume             carry on with the game saved when you last quit
+
+sim plays games with the bot as fast as possible and reports how it did:
+  --games <count>      how many games to play (default 10)
+  --seed <seed>        seed of the first game, counting up from there
+  --randomizer <name>  classic (default) or bag
+  --max-pieces <count> cut games off after this many pieces (default 10000)
+  --csv                print the results as CSV
+
+tune evolves bot weights, starting from --weights if given:
+  --population <count> weight sets per generation (default 20)
+  --generations <count> generations to run (default 20)
+  --games <count>      games per weight set per generation (default 4)
+  --max-pieces <count> cut games off after this many pieces (default 500)
+  --randomizer <name>  classic (default) or bag
+  --seed <seed>        seed for breeding
+  --checkpoint <file>  where to save progress, and resume from
+  --out <file>         save the best weights here
+
+tournament plays bots against each other in versus and rates them:
+  --bots <file>        a JSON list of entrants, each with a name, a type of
+                       heuristic (with optional weights and depth) or tbp
+                       (with a command), and an optional pps. Defaults to the
+                       built-in bot at each difficulty
+  --format <name>      round-robin (default) or swiss
+  --rounds <count>     swiss rounds to play (default enough for a winner)
+  --first-to <count>   games needed to win a match (default 3)
+  --time-limit <seconds> draw games that go on this long (default 300)
+  --seed <seed>        seed for the games' pieces
+  --ratings <file>     Elo ratings to update (default ratings.json in the
+                       data directory)
+  --logs <dir>         where to save each game, to open with --replay
+
+fumen prints a fumen URL of a game:
+  --replay <file>      a page for every piece placed in a recorded game
+  --resume             the board and pieces of the saved game";
+
+const TICK_RATE: Duration = Duration::from_nanos(1_000_000_000 / FRAMES_PER_SECOND as u64);
+
+struct Options {
+    mode: Mode,
+    replay: Option<PathBuf>,
+    resume: bool,
+    // Where to start from, if imported from fumen.
+    setup: Option<Setup>,
+    versus: bool,
+    host: Option<u16>,
+    join: Option<String>,
+    watch: Option<String>,
+    garbage: GarbageRules,
+    bot: bool,
+    // How many upcoming pieces the bot plans around, and how fast it's
+    // allowed to play.
+    depth: usize,
+    pps: Option<f64>,
+    difficulty: Option<String>,
+    tbp: bool,
+    tbp_bot: Option<String>,
+    weights: Option<PathBuf>,
+    subcommand: Option<Subcommand>,
+    batch: BatchOptions,
+}
+
+enum Subcommand {
+    Sim,
+    Tune,
+    Tournament,
+    Fumen,
+}
+
+// Options for sim, tune and tournament. Where they want different defaults,
+// they're left as None.
+struct BatchOptions {
+    games: Option<u64>,
+    seed: u64,
+    randomizer: Randomizer,
+    max_pieces: Option<u32>,
+    csv: bool,
+    population: usize,
+    generations: u32,
+    checkpoint: Option<PathBuf>,
+    out: Option<PathBuf>,
+    bots: Option<PathBuf>,
+    format: Format,
+    rounds: Option<u32>,
+    first_to: u32,
+    time_limit: u64,
+    ratings: Option<PathBuf>,
+    logs: Option<PathBuf>,
+}
+
+// Who's at the controls when the game isn't being replayed.
+enum Player {
+    Human,
+    Bot(Heuristic),
+    Tbp(Box<TbpClient>),
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut replay = None;
+    let mut resume = false;
+    let mut fumen = None;
+    let mut fumen_page = 1;
+    let mut versus = false;
+    let mut host = None;
+    let mut join = None;
+    let mut watch = None;
+    let mut garbage = GarbageRules::default();
+    let mut bot = false;
+    let mut depth = None;
+    let mut pps = None;
+    let mut difficulty = None;
+    let mut tbp = false;
+    let mut tbp_bot = None;
+    let mut weights = None;
+    let mut mode_name = String::from("marathon");
+    let mut marathon = Marathon::default();
+    let mut dig = Dig::default();
+    let mut zen = Zen::default();
+    let mut invisible = Invisible::default();
+    let mut lines = None;
+    let mut args = std::env::args().skip(1).peekable();
+    let subcommand = match args.peek().map(String::as_str) {
+        Some("sim") => Some(Subcommand::Sim),
+        Some("tune") => Some(Subcommand::Tune),
+        Some("tournament") => Some(Subcommand::Tournament),
+        Some("fumen") => Some(Subcommand::Fumen),
+        _ => None,
+    };
+    if subcommand.is_some() {
+        args.next();
+    }
+    let mut batch = BatchOptions {
+        games: None,
+        seed: 0,
+        randomizer: Randomizer::default(),
+        max_pieces: None,
+        csv: false,
+        population: 20,
+        generations: 20,
+        checkpoint: None,
+        out: None,
+        bots: None,
+        format: Format::default(),
+        rounds: None,
+        first_to: TournamentConfig::default().first_to,
+        time_limit: TournamentConfig::default().time_limit,
+        ratings: None,
+        logs: None,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mode" => mode_name = parse_value(&arg, args.next())?,
+            "--level" => {
+                let level = parse_value(&arg, args.next())?;
+                if !(MIN_START_LEVEL..=MAX_START_LEVEL).contains(&level) {
+                    return Err(format!(
+                        "--level must be between {MIN_START_LEVEL} and {MAX_START_LEVEL}"
+                    ));
+                }
+                marathon.start_level = level;
+            }
+            "--lines" => {
+                let count = parse_value(&arg, args.next())?;
+                if count == 0 {
+                    return Err(String::from("--lines must be at least 1"));
+                }
+                lines = Some(count);
+            }
+            "--endless" => marathon.endless = true,
+            "--messiness" => {
+                let messiness = parse_value(&arg, args.next())?;
+                if !(0.0..=1.0).contains(&messiness) {
+                    return Err(String::from("--messiness must be between 0 and 1"));
+                }
+                dig.messiness = messiness;
+            }
+            "--gravity" => zen.gravity = true,
+            "--fade" => invisible.fade_seconds = parse_value(&arg, args.next())?,
+            "--flash" => invisible.flash_on_clear = true,
+            "--replay" => replay = Some(parse_value(&arg, args.next())?),
+            "--resume" => resume = true,
+            "--fumen" => fumen = Some(parse_value::<String>(&arg, args.next())?),
+            "--fumen-page" => fumen_page = parse_value(&arg, args.next())?,
+            "--versus" => versus = true,
+            "--host" => host = Some(parse_value(&arg, args.next())?),
+            "--join" => join = Some(parse_value(&arg, args.next())?),
+            "--watch" => watch = Some(parse_value(&arg, args.next())?),
+            "--garbage-delay" => garbage.delay = parse_value(&arg, args.next())?,
+            "--garbage-holes" => {
+                let name: String = parse_value(&arg, args.next())?;
+                garbage.holes = HolePolicy::from_name(&name)
+                    .ok_or_else(|| format!("Unknown garbage holes: {name}"))?;
+            }
+            "--bot" => bot = true,
+            "--lookahead" => depth = Some(1),
+            "--pps" => {
+                let rate: f64 = parse_value(&arg, args.next())?;
+                if !(rate.is_finite() && rate > 0.0) {
+                    return Err(String::from("--pps must be a number above 0"));
+                }
+                pps = Some(rate);
+            }
+            "--difficulty" => difficulty = Some(parse_value::<String>(&arg, args.next())?),
+            "--tbp" => tbp = true,
+            "--tbp-bot" => tbp_bot = Some(parse_value(&arg, args.next())?),
+            "--weights" => weights = Some(parse_value(&arg, args.next())?),
+            "--games" => batch.games = Some(parse_value(&arg, args.next())?),
+            "--seed" => batch.seed = parse_value(&arg, args.next())?,
+            "--randomizer" => {
+                let name: String = parse_value(&arg, args.next())?;
+                batch.randomizer = Randomizer::from_name(&name)
+                    .ok_or_else(|| format!("Unknown randomizer: {name}"))?;
+            }
+            "--max-pieces" => batch.max_pieces = Some(parse_value(&arg, args.next())?),
+            "--csv" => batch.csv = true,
+            "--population" => batch.population = parse_value(&arg, args.next())?,
+            "--generations" => batch.generations = parse_value(&arg, args.next())?,
+            "--checkpoint" => batch.checkpoint = Some(parse_value(&arg, args.next())?),
+            "--out" => batch.out = Some(parse_value(&arg, args.next())?),
+            "--bots" => batch.bots = Some(parse_value(&arg, args.next())?),
+            "--format" => {
+                let name: String = parse_value(&arg, args.next())?;
+                batch.format = Format::from_name(&name)
+                    .ok_or_else(|| format!("Unknown format: {name}"))?;
+            }
+            "--rounds" => batch.rounds = Some(parse_value(&arg, args.next())?),
+            "--first-to" => batch.first_to = parse_value(&arg, args.next())?,
+            "--time-limit" => batch.time_limit = parse_value(&arg, args.next())?,
+            "--ratings" => batch.ratings = Some(parse_value(&arg, args.next())?),
+            "--logs" => batch.logs = Some(parse_value(&arg, args.next())?),
+            _ => return Err(format!("Unknown argument: {arg}")),
+        }
+    }
+    let mode = match mode_name.as_str() {
+        "marathon" => {
+            marathon.line_goal = lines.unwrap_or(marathon.line_goal);
+            Mode::Marathon(marathon)
+        }
+        "dig" => {
+            dig.lines = lines.unwrap_or(dig.lines);
+            Mode::Dig(dig)
+        }
+        "master" => Mode::Master,
+        "zen" => Mode::Zen(zen),
+        "invisible" => Mode::Invisible(invisible),
+        "finesse" => Mode::Finesse,
+        _ => return Err(format!("Unknown mode: {mode_name}")),
+    };
+    if [host.is_some(), join.is_some(), watch.is_some()].into_iter().filter(|&given| given).count() > 1 {
+        return Err(String::from("Only one of --host, --join and --watch can be given"));
+    }
+    if difficulty.is_none() && versus && bot {
+        difficulty = Some(String::from("medium"));
+    }
+    if let Some(name) = &difficulty {
+        let preset = Difficulty::from_name(name).ok_or_else(|| format!(
+            "Unknown difficulty: {name}, expected one of {}", Difficulty::NAMES.join(", "),
+        ))?;
+        depth = depth.or(Some(preset.depth));
+        pps = pps.or(Some(preset.pps));
+    }
+    let depth = depth.unwrap_or(0);
+    let setup = match fumen {
+        Some(data) => Some(fumen::import(&data, fumen_page)
+            .map_err(|err| format!("Couldn't import fumen: {err}"))?),
+        None => None,
+    };
+    Ok(Options {
+        mode, replay, resume, setup, versus, host, join, watch, garbage, bot, depth, pps, difficulty, tbp, tbp_bot, weights, subcommand, batch,
+    })
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{flag} requires a value"))?;
+    value.parse().map_err(|_| format!("Invalid value for {flag}: {value}"))
+}
 
 fn main() -> Result<(), io::Error> {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    let weights = match &options.weights {
+        Some(path) => match Weights::load(path) {
+            Ok(weights) => weights,
+            Err(err) => {
+                eprintln!("Couldn't load weights {}: {err}", path.display());
+                std::process::exit(1);
+            }
+        },
+        None => Weights::default(),
+    };
+    if let Some(subcommand) = &options.subcommand {
+        let res = match subcommand {
+            Subcommand::Sim => run_sim(&options, Heuristic::new(weights, options.depth)),
+            Subcommand::Tune => run_tune(&options, &weights),
+            Subcommand::Tournament => run_tournament(&options),
+            Subcommand::Fumen => run_fumen(&options),
+        };
+        if let Err(err) = res {
+            eprintln!("Error: {err}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if options.tbp {
+        let heuristic = Heuristic::new(weights, options.depth);
+        return tbp::serve(io::stdin().lock(), io::stdout().lock(), &heuristic);
+    }
+    let player = match &options.tbp_bot {
+        Some(command) => match TbpClient::launch(command) {
+            Ok(client) => Player::Tbp(Box::new(client)),
+            Err(err) => {
+                eprintln!("Couldn't start bot {command}: {err}");
+                std::process::exit(1);
+            }
+        },
+        None if options.bot => Player::Bot(Heuristic::new(weights, options.depth)),
+        None => Player::Human,
+    };
+    let playback = match &options.replay {
+        Some(path) => match Recording::load(path) {
+            Ok(replay) => Some(replay),
+            Err(err) => {
+                eprintln!("Couldn't load replay {}: {err}", path.display());
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let resumed = if options.resume {
+        let path = SavedGame::default_path();
+        match SavedGame::load(&path) {
+            Ok(saved) => Some(saved),
+            Err(err) => {
+                eprintln!("Couldn't resume saved game {}: {err}", path.display());
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    // Networked matches are set up before the terminal is taken over, so
+    // there's somewhere to say what's happening while waiting.
+    let net = if let Some(port) = options.host {
+        println!("Waiting for someone to join on port {port}...");
+        Some(net::host(port, options.mode.clone(), rand::random(), options.garbage))
+    } else {
+        options.join.as_ref().map(|addr| net::join(addr))
+    };
+    let net = match net.transpose() {
+        Ok(net) => net,
+        Err(err) => {
+            eprintln!("Couldn't start the match: {err}");
+            std::process::exit(1);
+        }
+    };
+    let spectating = match options.watch.as_ref().map(|addr| net::watch(addr)).transpose() {
+        Ok(spectating) => spectating,
+        Err(err) => {
+            eprintln!("Couldn't watch the match: {err}");
+            std::process::exit(1);
+        }
+    };
+    let scores_path = HighScores::default_path();
+    let mut scores = match HighScores::load(&scores_path) {
+        Ok(scores) => scores,
+        Err(err) => {
+            eprintln!("Couldn't load high scores {}: {err}", scores_path.display());
+            std::process::exit(1);
+        }
+    };
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(
@@ -31,9 +462,16 @@ fn main() -> Result<(), io::Error> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let tick_rate = Duration::from_millis(16);
-    let game = Game::new();
-    let res = tetris::run_game(&mut terminal, game, tick_rate);
+    // Anything worth telling the player is printed once the terminal is back
+    // to normal.
+    let mut messages = Vec::new();
+    let res = match (net, spectating) {
+        (Some(mut net), _) => run_net(&mut terminal, &mut net, player, &options),
+        (None, Some(mut spectating)) => tetris::run_watch(&mut terminal, &mut spectating, TICK_RATE),
+        (None, None) => run(
+            &mut terminal, &options, playback, resumed, player, &mut scores, &mut messages,
+        ),
+    };
 
     disable_raw_mode()?;
     execute!(
@@ -46,6 +484,304 @@ fn main() -> Result<(), io::Error> {
     if let Err(err) = res {
         println!("Error: {err:?}");
     }
+    for message in messages {
+        println!("{message}");
+    }
+
+    Ok(())
+}
 
+fn run_sim(options: &Options, mut heuristic: Heuristic) -> io::Result<()> {
+    let batch = &options.batch;
+    let games = batch.games.unwrap_or(10);
+    let last_seed = batch.seed.checked_add(games).ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidInput, "--seed is too large to play that many --games from",
+    ))?;
+    let seeds = batch.seed..last_seed;
+    let max_pieces = batch.max_pieces.unwrap_or(10_000);
+    let results = match &options.tbp_bot {
+        Some(command) => {
+            let mut client = TbpClient::launch(command)?;
+            tetris::sim::run(&mut client, &options.mode, batch.randomizer, seeds, max_pieces)?
+        }
+        None => {
+            tetris::sim::run(&mut heuristic, &options.mode, batch.randomizer, seeds, max_pieces)?
+        }
+    };
+    let summary = tetris::sim::summarize(&results);
+    if batch.csv {
+        print!("{}", tetris::sim::format_csv(&summary));
+    } else {
+        println!(
+            "{} games of {} with the {} randomizer, seeds from {}",
+            games, options.mode.name(), batch.randomizer.name(), batch.seed,
+        );
+        print!("{}", tetris::sim::format_table(&summary));
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+// Runs the tuner, saving a checkpoint after every generation. An existing
+// checkpoint is picked up where it left off.
+fn run_tune(options: &Options, start: &Weights) -> io::Result<()> {
+    let batch = &options.batch;
+    let defaults = TuneConfig::default();
+    let config = TuneConfig {
+        mode: options.mode.clone(),
+        randomizer: batch.randomizer,
+        games: batch.games.unwrap_or(defaults.games),
+        max_pieces: batch.max_pieces.unwrap_or(defaults.max_pieces),
+        ..defaults
+    };
+    let checkpoint = batch.checkpoint.clone()
+        .unwrap_or_else(|| storage::data_dir().join("tune-checkpoint.json"));
+    let mut tuner = if checkpoint.exists() {
+        let tuner = Tuner::load(&checkpoint)?;
+        println!("Resuming from generation {}", tuner.generation());
+        tuner
+    } else {
+        Tuner::new(batch.population, start, batch.seed)
+    };
+    while tuner.generation() < batch.generations {
+        let scored = tuner.step(&config)?;
+        let mean = scored.iter().map(|member| member.fitness).sum::<f64>() / scored.len() as f64;
+        println!(
+            "Generation {:>3}: best {:>10.1}  mean {:>10.1}",
+            tuner.generation(), scored[0].fitness, mean,
+        );
+        tuner.save(&checkpoint)?;
+    }
+    let Some(best) = tuner.best() else {
+        return Ok(());
+    };
+    println!("Best weights, scoring {:.1}:", best.fitness);
+    println!("{}", serde_json::to_string_pretty(&best.weights)?);
+    if let Some(out) = &batch.out {
+        best.weights.save(out)?;
+        println!("Saved to {}", out.display());
+    }
+    Ok(())
+}
+
+// Runs a tournament between the bots listed in --bots, or the built-in ones,
+// saving the updated ratings after every match.
+fn run_tournament(options: &Options) -> io::Result<()> {
+    let batch = &options.batch;
+    let entrants = match &batch.bots {
+        Some(path) => Entrant::load_all(path)?,
+        None => Entrant::built_in(),
+    };
+    let config = TournamentConfig {
+        mode: options.mode.clone(),
+        garbage: options.garbage,
+        format: batch.format,
+        rounds: batch.rounds,
+        first_to: batch.first_to,
+        time_limit: batch.time_limit,
+        seed: batch.seed,
+    };
+    let ratings_path = batch.ratings.clone().unwrap_or_else(Ratings::default_path);
+    let mut ratings = Ratings::load_or_default(&ratings_path)?;
+    let logs = batch.logs.clone().unwrap_or_else(|| {
+        storage::data_dir()
+            .join("tournaments")
+            .join(format!("tournament-{}", storage::unix_time()))
+    });
+    println!(
+        "{} tournament of {} between {} bots, first to {}",
+        config.format.name(), config.mode.name(), entrants.len(), config.first_to,
+    );
+    let standings = tournament::run(&entrants, &config, &mut ratings, &logs, |result, ratings| {
+        let [first, second] = result.players;
+        let draws = match result.draws {
+            0 => String::new(),
+            draws => format!(" ({draws} drawn)"),
+        };
+        println!(
+            "Round {:>2}: {} {}-{} {}{draws}",
+            result.round, entrants[first].name, result.wins[0], result.wins[1], entrants[second].name,
+        );
+        ratings.save(&ratings_path)
+    })?;
+    print!("{}", tournament::format_standings(&standings, &ratings));
+    println!("Games saved to {}", logs.display());
+    println!("Ratings saved to {}", ratings_path.display());
+    Ok(())
+}
+
+// Prints a fumen URL for the replay given with --replay, or the saved game.
+fn run_fumen(options: &Options) -> io::Result<()> {
+    let pages = match (&options.replay, options.resume) {
+        (Some(path), _) => match Recording::load(path)? {
+            Recording::Single(replay) => fumen::replay_pages(&replay),
+            Recording::Versus(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "versus replays can't be exported to fumen",
+                ));
+            }
+        },
+        (None, true) => {
+            let saved = SavedGame::load(&SavedGame::default_path())?;
+            vec![Page::from_setup(&saved.game.current_setup())]
+        }
+        (None, false) => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "fumen needs --replay or --resume"));
+        }
+    };
+    println!("{}{}", fumen::URL_PREFIX, fumen::encode(&pages));
+    Ok(())
+}
+
+fn run_net<B: Backend>(
+    terminal: &mut Terminal<B>,
+    net: &mut NetMatch,
+    player: Player,
+    options: &Options,
+) -> io::Result<()> {
+    match player {
+        Player::Tbp(client) => {
+            tetris::run_net(terminal, net, Some(&mut new_bot(*client, options)), TICK_RATE)
+        }
+        Player::Bot(heuristic) => {
+            tetris::run_net(terminal, net, Some(&mut new_bot(heuristic, options)), TICK_RATE)
+        }
+        Player::Human => tetris::run_net(terminal, net, None::<&mut Bot>, TICK_RATE),
+    }
+}
+
+fn new_bot<P: Planner>(planner: P, options: &Options) -> Bot<P> {
+    match options.pps {
+        Some(pps) => Bot::new(planner).with_pps_cap(pps),
+        None => Bot::new(planner),
+    }
+}
+
+fn run<B: Backend>(
+    terminal: &mut Terminal<B>,
+    options: &Options,
+    playback: Option<Recording>,
+    resumed: Option<SavedGame>,
+    player: Player,
+    scores: &mut HighScores,
+    messages: &mut Vec<String>,
+) -> io::Result<()> {
+    match &playback {
+        Some(Recording::Single(replay)) => return tetris::run_replay(terminal, replay, TICK_RATE),
+        Some(Recording::Versus(replay)) => return tetris::run_versus_replay(terminal, replay, TICK_RATE),
+        None => (),
+    }
+    let mode = &options.mode;
+    if options.versus {
+        let mut versus = Versus::with_rules(mode.clone(), rand::random(), options.garbage);
+        let name = match &options.difficulty {
+            Some(difficulty) => format!("Bot ({difficulty})"),
+            None => String::from("Bot"),
+        };
+        return match player {
+            Player::Tbp(client) => {
+                let mut bot = new_bot(*client, options);
+                tetris::run_versus_bot(terminal, &mut versus, &mut bot, &name, TICK_RATE)
+            }
+            Player::Bot(heuristic) => {
+                let mut bot = new_bot(heuristic, options);
+                tetris::run_versus_bot(terminal, &mut versus, &mut bot, &name, TICK_RATE)
+            }
+            Player::Human => tetris::run_versus(terminal, &mut versus, TICK_RATE),
+        };
+    }
+    match player {
+        Player::Tbp(client) => {
+            let mut game = new_game(options);
+            return tetris::run_bot(terminal, &mut game, &mut new_bot(*client, options), TICK_RATE);
+        }
+        Player::Bot(heuristic) => {
+            let mut game = new_game(options);
+            return tetris::run_bot(terminal, &mut game, &mut new_bot(heuristic, options), TICK_RATE);
+        }
+        Player::Human => (),
+    }
+    if let Some(saved) = resumed {
+        play(terminal, saved.game, saved.replay, true, scores, messages)?;
+    }
+    // The editor starts from the imported fumen page, if any, and keeps its
+    // board between visits.
+    let mut setup = options.setup.clone().unwrap_or_default();
+    loop {
+        match run_menu(terminal, mode, scores)? {
+            MenuChoice::Play => {
+                let game = new_game(options);
+                let replay = Replay::for_game(&game);
+                play(terminal, game, replay, false, scores, messages)?;
+            }
+            // Games from the editor are played like any other, but they're
+            // kept out of the high scores along with every other setup.
+            MenuChoice::Edit => {
+                if let EditorChoice::Play = run_editor(terminal, &mut setup, messages)? {
+                    let game = Game::with_setup(
+                        mode.clone(), rand::random(), Randomizer::default(), setup.clone(),
+                    );
+                    let replay = Replay::for_game(&game);
+                    play(terminal, game, replay, false, scores, messages)?;
+                }
+            }
+            MenuChoice::Quit => return Ok(()),
+        }
+    }
+}
+
+// A fresh game in the chosen mode, starting from the imported fumen page if
+// there is one.
+fn new_game(options: &Options) -> Game {
+    match &options.setup {
+        Some(setup) => Game::with_setup(
+            options.mode.clone(), rand::random(), Randomizer::default(), setup.clone(),
+        ),
+        None => Game::with_mode(options.mode.clone()),
+    }
+}
+
+fn play<B: Backend>(
+    terminal: &mut Terminal<B>,
+    mut game: Game,
+    mut replay: Replay,
+    resumed: bool,
+    scores: &mut HighScores,
+    messages: &mut Vec<String>,
+) -> io::Result<()> {
+    let old_scores = scores.clone();
+    tetris::run_game(terminal, &mut game, &mut replay, scores, TICK_RATE)?;
+    if *scores != old_scores {
+        if let Err(err) = scores.save(&HighScores::default_path()) {
+            messages.push(format!("Error saving high scores: {err:?}"));
+        }
+    }
+    finish_session(game, replay, resumed, messages);
+    Ok(())
+}
+
+// Saves an unfinished game so it can be resumed later. Once a game is over its
+// replay is written out, and the save it was resumed from is no longer needed.
+fn finish_session(game: Game, recording: Replay, resumed: bool, messages: &mut Vec<String>) {
+    let save_path = SavedGame::default_path();
+    if !game.is_over() {
+        match SavedGame::new(game, recording).save(&save_path) {
+            Ok(()) => messages.push(String::from("Game saved, continue it with --resume")),
+            Err(err) => messages.push(format!("Error saving game: {err:?}")),
+        }
+        return;
+    }
+
+    if resumed {
+        if let Err(err) = fs::remove_file(&save_path) {
+            messages.push(format!("Error removing saved game: {err:?}"));
+        }
+    }
+    if recording.frames() > 0 {
+        let path = Replay::default_path();
+        match recording.save(&path) {
+            Ok(()) => messages.push(format!("Replay saved to {}", path.display())),
+            Err(err) => messages.push(format!("Error saving replay: {err:?}")),
+        }
+    }
+}
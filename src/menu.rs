@@ -0,0 +1,153 @@
+use std::io;
+
+use crossterm::event;
+use crossterm::event::{Event, KeyCode};
+use ratatui::{Frame, Terminal};
+use ratatui::backend::Backend;
+use ratatui::layout::{Alignment, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, BorderType, Paragraph};
+
+use crate::format_time;
+use crate::mode::Mode;
+use crate::scores::{HighScores, MAX_ENTRIES};
+use crate::storage;
+
+pub enum MenuChoice {
+    Play,
+    Edit,
+    Quit,
+}
+
+const MENU_ITEMS: [&str; 4] = ["Play", "Edit board", "High scores", "Quit"];
+const EDIT_ITEM: usize = 1;
+const HIGH_SCORES_ITEM: usize = 2;
+
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+fn render_menu<B: Backend>(f: &mut Frame<B>, mode: &Mode, selected: usize) {
+    let mut lines = vec![
+        Line::from(format!("Mode: {}", mode.name())),
+        Line::from(""),
+    ];
+    for (i, item) in MENU_ITEMS.iter().enumerate() {
+        if i == selected {
+            lines.push(Line::styled(
+                format!("> {item}"),
+                Style::new().add_modifier(Modifier::BOLD),
+            ));
+        } else {
+            lines.push(Line::from(format!("  {item}")));
+        }
+    }
+    let menu = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .block(Block::default()
+            .title("Tetris")
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double));
+    f.render_widget(menu, centered(f.size(), 30, MENU_ITEMS.len() as u16 + 4));
+}
+
+fn render_high_scores<B: Backend>(f: &mut Frame<B>, scores: &HighScores, mode_name: &str) {
+    let (is_race, entries) = match scores.table(mode_name) {
+        Some(table) => (table.race, table.entries.as_slice()),
+        None => (false, [].as_slice()),
+    };
+    let mut lines = vec![Line::styled(
+        format!(
+            " #  Name  {:>9}  Lines  Level  Date        Seed",
+            if is_race { "Time" } else { "Score" },
+        ),
+        Style::new().add_modifier(Modifier::BOLD),
+    )];
+    for (i, entry) in entries.iter().enumerate() {
+        let result = if is_race {
+            format_time(std::time::Duration::from_millis(entry.time_ms))
+        } else {
+            format!("{}", entry.score)
+        };
+        lines.push(Line::from(format!(
+            "{:>2}  {:<4}  {:>9}  {:>5}  {:>5}  {}  {}",
+            i + 1,
+            entry.name,
+            result,
+            entry.lines,
+            entry.level,
+            storage::format_date(entry.date),
+            entry.seed,
+        )));
+    }
+    if entries.is_empty() {
+        lines.push(Line::from("No games recorded yet"));
+    }
+    let board = Paragraph::new(lines)
+        .block(Block::default()
+            .title(format!("< {mode_name} >"))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double));
+    f.render_widget(board, centered(f.size(), 78, MAX_ENTRIES as u16 + 3));
+}
+
+// Shows the high score tables, starting with the given mode's. Left and right
+// switch between modes.
+fn show_high_scores<B: Backend>(
+    terminal: &mut Terminal<B>,
+    scores: &HighScores,
+    mode: &Mode,
+) -> io::Result<()> {
+    let mut mode_names: Vec<String> = scores.modes().cloned().collect();
+    if !mode_names.contains(&mode.name()) {
+        mode_names.push(mode.name());
+        mode_names.sort();
+    }
+    let mut current = mode_names.iter().position(|name| *name == mode.name()).unwrap();
+    loop {
+        terminal.draw(|f| render_high_scores(f, scores, &mode_names[current]))?;
+        if let Event::Key(key) = event::read()? {
+            match key.code {
+                KeyCode::Esc | KeyCode::Enter => return Ok(()),
+                KeyCode::Left => current = (current + mode_names.len() - 1) % mode_names.len(),
+                KeyCode::Right => current = (current + 1) % mode_names.len(),
+                _ => (),
+            }
+        }
+    }
+}
+
+pub fn run_menu<B: Backend>(
+    terminal: &mut Terminal<B>,
+    mode: &Mode,
+    scores: &HighScores,
+) -> io::Result<MenuChoice> {
+    let mut selected = 0;
+    loop {
+        terminal.draw(|f| render_menu(f, mode, selected))?;
+        if let Event::Key(key) = event::read()? {
+            match key.code {
+                KeyCode::Esc => return Ok(MenuChoice::Quit),
+                KeyCode::Up => selected = (selected + MENU_ITEMS.len() - 1) % MENU_ITEMS.len(),
+                KeyCode::Down => selected = (selected + 1) % MENU_ITEMS.len(),
+                KeyCode::Enter => match selected {
+                    0 => return Ok(MenuChoice::Play),
+                    EDIT_ITEM => return Ok(MenuChoice::Edit),
+                    HIGH_SCORES_ITEM => show_high_scores(terminal, scores, mode)?,
+                    _ => return Ok(MenuChoice::Quit),
+                },
+                _ => (),
+            }
+        }
+    }
+}
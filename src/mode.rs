@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+
+pub const MIN_START_LEVEL: u32 = 1;
+pub const MAX_START_LEVEL: u32 = 15;
+pub const MAX_LEVEL: u32 = 15;
+pub const LINES_PER_LEVEL: u32 = 10;
+pub const DEFAULT_LINE_GOAL: u32 = 150;
+pub const DEFAULT_DIG_LINES: u32 = 18;
+pub const DEFAULT_MESSINESS: f64 = 1.0;
+pub const DIG_VISIBLE_ROWS: u32 = 10;
+pub const MASTER_MAX_LEVEL: u32 = 999;
+
+pub const FRAMES_PER_SECOND: u32 = 60;
+// Gravity is measured in cells per frame. Anything at or above 20G drops the
+// piece straight to the floor, so pieces effectively spawn at their ghost.
+pub const GRAVITY_20G: f64 = 20.0;
+
+// The endless marathon's speed-up by score.
+const ENDLESS_FRAMES_PER_ROW: f64 = 30.0;
+const ENDLESS_SPEED_UP: f64 = 0.8;
+const ENDLESS_POINTS_PER_SPEED_UP: i32 = 1000;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Mode {
+    Marathon(Marathon),
+    Dig(Dig),
+    Master,
+    Zen(Zen),
+    Invisible(Invisible),
+    // Finesse trainer: each piece gets a target placement, shown along with
+    // the shortest key sequence to reach it. Placements that miss the target
+    // or take extra keys are called out. Like zen, it has no gravity and no
+    // game over.
+    Finesse,
+}
+
+// Delays, in frames. ARE is the pause between a piece locking and the next
+// one spawning; line clears add their own delay on top of it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timings {
+    pub are: u32,
+    pub line_clear: u32,
+    pub lock_delay: u32,
+}
+
+const GUIDELINE_TIMINGS: Timings = Timings {
+    are: 0,
+    line_clear: 0,
+    lock_delay: 30,
+};
+
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::Marathon(Marathon::default())
+    }
+}
+
+// Guideline marathon: the level goes up every 10 lines and the game is won
+// once the line goal is reached. An endless marathon never ends except by
+// topping out, and keeps the speed-up from before there were levels too.
+// Every 1000 points cuts the time a row takes to fall by a fifth, starting
+// from half a second.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Marathon {
+    pub start_level: u32,
+    pub line_goal: u32,
+    pub endless: bool,
+}
+
+impl Default for Marathon {
+    fn default() -> Self {
+        Self {
+            start_level: MIN_START_LEVEL,
+            line_goal: DEFAULT_LINE_GOAL,
+            endless: false,
+        }
+    }
+}
+
+impl Mode {
+    pub fn start_level(&self) -> u32 {
+        match self {
+            Mode::Marathon(marathon) => marathon.level_for_lines(0),
+            Mode::Dig(_) | Mode::Zen(_) | Mode::Invisible(_) | Mode::Finesse => MIN_START_LEVEL,
+            Mode::Master => 0,
+        }
+    }
+
+    // Endless gravity goes by whichever of the level and the score is
+    // further along.
+    pub fn gravity(&self, level: u32, score: i32) -> f64 {
+        match self {
+            Mode::Marathon(marathon) if marathon.endless => {
+                guideline_gravity(level).max(endless_gravity(score))
+            }
+            Mode::Marathon(_) | Mode::Dig(_) | Mode::Invisible(_) => guideline_gravity(level),
+            Mode::Master => master_gravity(level),
+            Mode::Zen(zen) if zen.gravity => guideline_gravity(level),
+            Mode::Zen(_) | Mode::Finesse => 0.0,
+        }
+    }
+
+    pub fn timings(&self, level: u32) -> Timings {
+        match self {
+            Mode::Marathon(_) | Mode::Dig(_) | Mode::Zen(_) | Mode::Invisible(_)
+            | Mode::Finesse => GUIDELINE_TIMINGS,
+            Mode::Master => master_timings(level),
+        }
+    }
+
+    // Line clear points are multiplied by the level in the guideline modes,
+    // and by the section (one per hundred levels) in Master.
+    pub fn score_multiplier(&self, level: u32) -> u32 {
+        match self {
+            Mode::Marathon(_) | Mode::Dig(_) | Mode::Zen(_) | Mode::Invisible(_)
+            | Mode::Finesse => level,
+            Mode::Master => level / 100 + 1,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Mode::Marathon(marathon) if marathon.endless => String::from("Endless"),
+            Mode::Marathon(marathon) if marathon.line_goal == DEFAULT_LINE_GOAL => {
+                String::from("Marathon")
+            }
+            Mode::Marathon(marathon) => format!("Marathon ({} lines)", marathon.line_goal),
+            Mode::Dig(dig) => format!("Dig ({} lines)", dig.lines),
+            Mode::Master => String::from("Master"),
+            Mode::Zen(_) => String::from("Zen"),
+            Mode::Invisible(invisible) if invisible.fade_seconds == 0 => {
+                String::from("Invisible")
+            }
+            Mode::Invisible(invisible) => format!("Fading ({}s)", invisible.fade_seconds),
+            Mode::Finesse => String::from("Finesse"),
+        }
+    }
+
+    // What's shown when a game ends by reaching the mode's goal. None if the
+    // mode has no goal to reach.
+    pub fn completion_message(&self) -> Option<&'static str> {
+        match self {
+            Mode::Marathon(_) => Some("Marathon complete!"),
+            Mode::Dig(_) => Some("Dig complete!"),
+            Mode::Master => Some("Master complete!"),
+            Mode::Zen(_) | Mode::Invisible(_) | Mode::Finesse => None,
+        }
+    }
+
+    // Races are ranked by fastest time, everything else by score.
+    pub fn is_race(&self) -> bool {
+        matches!(self, Mode::Dig(_))
+    }
+
+    // Practice modes never end: topping out just empties the board.
+    pub fn clears_on_top_out(&self) -> bool {
+        matches!(self, Mode::Zen(_) | Mode::Finesse)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        matches!(self, Mode::Zen(_))
+    }
+}
+
+// Guideline gravity: seconds per row = (0.8 - (level - 1) * 0.007) ^ (level - 1).
+fn guideline_gravity(level: u32) -> f64 {
+    let n = (level - 1) as f64;
+    let seconds_per_row = (0.8 - n * 0.007).powf(n);
+    1.0 / (seconds_per_row * FRAMES_PER_SECOND as f64)
+}
+
+fn endless_gravity(score: i32) -> f64 {
+    let speed_ups = score.max(0) / ENDLESS_POINTS_PER_SPEED_UP;
+    1.0 / (ENDLESS_FRAMES_PER_ROW * ENDLESS_SPEED_UP.powi(speed_ups))
+}
+
+// TGM gravity curve, as (starting level, gravity in 1/256ths of a cell per frame).
+const MASTER_GRAVITY: [(u32, u32); 30] = [
+    (0, 4), (30, 6), (35, 8), (40, 10), (50, 12), (60, 16), (70, 32), (80, 48),
+    (90, 64), (100, 80), (120, 96), (140, 112), (160, 128), (170, 144), (200, 4),
+    (220, 32), (230, 64), (233, 96), (236, 128), (239, 160), (243, 192), (247, 224),
+    (251, 256), (300, 512), (330, 768), (360, 1024), (400, 1280), (420, 1024),
+    (450, 768), (500, 5120),
+];
+
+fn master_gravity(level: u32) -> f64 {
+    let (_, gravity) = MASTER_GRAVITY.iter()
+        .rev()
+        .find(|(start, _)| level >= *start)
+        .unwrap();
+    *gravity as f64 / 256.0
+}
+
+// Once gravity hits 20G at level 500, every section shortens the delays.
+const MASTER_TIMINGS: [(u32, Timings); 6] = [
+    (0, Timings { are: 25, line_clear: 40, lock_delay: 30 }),
+    (500, Timings { are: 25, line_clear: 25, lock_delay: 30 }),
+    (600, Timings { are: 16, line_clear: 16, lock_delay: 26 }),
+    (700, Timings { are: 12, line_clear: 12, lock_delay: 22 }),
+    (800, Timings { are: 6, line_clear: 6, lock_delay: 18 }),
+    (900, Timings { are: 6, line_clear: 6, lock_delay: 15 }),
+];
+
+fn master_timings(level: u32) -> Timings {
+    let (_, timings) = MASTER_TIMINGS.iter()
+        .rev()
+        .find(|(start, _)| level >= *start)
+        .unwrap();
+    *timings
+}
+
+// The level goes up every 10 lines from where it started, up to the cap.
+fn level_for_lines(start_level: u32, lines: u32) -> u32 {
+    (start_level + lines / LINES_PER_LEVEL).min(MAX_LEVEL)
+}
+
+impl Marathon {
+    pub fn level_for_lines(&self, lines: u32) -> u32 {
+        level_for_lines(self.start_level, lines)
+    }
+
+    pub fn is_complete(&self, lines: u32) -> bool {
+        !self.endless && lines >= self.line_goal
+    }
+}
+
+// Cheese race: the bottom of the board starts filled with garbage and the game
+// is won once every garbage row has been cleared. At most DIG_VISIBLE_ROWS are
+// on the board at once; the rest are pushed in from below as rows are dug out.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Dig {
+    pub lines: u32,
+    pub messiness: f64,
+}
+
+impl Default for Dig {
+    fn default() -> Self {
+        Self {
+            lines: DEFAULT_DIG_LINES,
+            messiness: DEFAULT_MESSINESS,
+        }
+    }
+}
+
+// Practice mode: no goal and no game over, since topping out just clears the
+// board. Every placement can be undone and redone.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Zen {
+    pub gravity: bool,
+}
+
+// Locked blocks disappear from view, either straight away or after
+// fade_seconds, though they still block pieces as normal. Levels go up every
+// 10 lines as in an endless marathon.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Invisible {
+    pub fade_seconds: u32,
+    pub flash_on_clear: bool,
+}
+
+impl Invisible {
+    pub fn level_for_lines(&self, lines: u32) -> u32 {
+        level_for_lines(MIN_START_LEVEL, lines)
+    }
+}
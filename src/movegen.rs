@@ -0,0 +1,95 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::game::{Board, Command};
+use crate::tetromino::{PositionedTetromino, Tetromino};
+
+// The moves searched through to find where a piece can go.
+pub const SEARCH_MOVES: [Command; 5] = [
+    Command::Left,
+    Command::Right,
+    Command::Clockwise,
+    Command::CounterClockwise,
+    Command::Drop,
+];
+
+// A spot a piece can lock in, and the commands that get it there from where
+// the search started. The path always ends with a hard drop.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placement {
+    pub piece: PositionedTetromino,
+    pub path: Vec<Command>,
+    pub t_spin: bool,
+}
+
+impl Placement {
+    // The board with the piece locked in, before any lines are cleared.
+    pub fn apply(&self, board: &Board) -> Board {
+        let mut board = *board;
+        for (y, x) in self.piece.get_coords() {
+            board[y as usize][x as usize] = self.piece.get_cell_type();
+        }
+        board
+    }
+}
+
+// Where a command moves the piece to, leaving out gravity and locking.
+pub fn apply(piece: &PositionedTetromino, command: Command, board: &Board) -> PositionedTetromino {
+    let mut moved = piece.clone();
+    match command {
+        Command::Left => moved.move_left(board),
+        Command::Right => moved.move_right(board),
+        Command::Clockwise => moved.rotate_cw(board),
+        Command::CounterClockwise => moved.rotate_ccw(board),
+        Command::Drop => moved.move_down(board),
+        Command::HardDrop => moved.drop_to_floor(board),
+        _ => (),
+    }
+    moved
+}
+
+// Every placement for a fresh piece of the given type, starting from where
+// it would spawn. Empty if the piece can't spawn at all.
+pub fn placements(board: &Board, tetromino: &Tetromino) -> Vec<Placement> {
+    match PositionedTetromino::place(tetromino, board) {
+        Some(start) => placements_from(&start, board),
+        None => Vec::new(),
+    }
+}
+
+// Every placement the piece can reach from `start`, tucks and spins
+// included, each with the shortest command sequence to it. Gravity is
+// ignored, so the paths assume the piece only falls when told to.
+//
+// States are positions together with whether the last move was a rotation,
+// so a T piece spun into a slot is kept apart from one slid in, since only
+// the first scores as a T-spin.
+pub fn placements_from(start: &PositionedTetromino, board: &Board) -> Vec<Placement> {
+    let mut seen = HashSet::from([(start.clone(), false)]);
+    let mut found = HashSet::new();
+    let mut queue = VecDeque::from([(start.clone(), false, Vec::new())]);
+    let mut placements = Vec::new();
+    while let Some((piece, rotated, path)) = queue.pop_front() {
+        let mut landed = piece.clone();
+        landed.drop_to_floor(board);
+        let t_spin = landed == piece && rotated && piece.is_t_spin_position(board);
+        if found.insert((landed.clone(), t_spin)) {
+            let mut path = path.clone();
+            path.push(Command::HardDrop);
+            placements.push(Placement { piece: landed, path, t_spin });
+        }
+
+        for command in SEARCH_MOVES {
+            let moved = apply(&piece, command, board);
+            if moved == piece {
+                continue;
+            }
+            let rotated = matches!(command, Command::Clockwise | Command::CounterClockwise);
+            if seen.insert((moved.clone(), rotated)) {
+                let mut moved_path = path.clone();
+                moved_path.push(command);
+                queue.push_back((moved, rotated, moved_path));
+            }
+        }
+    }
+    placements
+}
@@ -0,0 +1,554 @@
+// Versus over TCP. Each side plays its own game and sends every command it
+// ticks with to the other, which runs a copy of that game to show. Attacks
+// are sent as they happen, and every second each side sends a hash of its
+// game so the other can check its copy hasn't drifted.
+//
+// Spectators connect to the host too, answering its hello with a watch. The
+// host relays both players' messages to them, starting with everything sent
+// so far, so they can run replicas of both games.
+//
+// Every message is JSON, preceded by its length as a 4-byte big-endian
+// integer.
+
+use std::io::{self, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Command, Game};
+use crate::garbage::GarbageRules;
+use crate::mode::Mode;
+use crate::replay::Inputs;
+use crate::versus::{self, PLAYERS, Versus};
+
+// Bump this whenever the messages change, so mismatched versions refuse to
+// play rather than drift apart.
+pub const PROTOCOL_VERSION: u32 = 4;
+
+// The host is player one, and whoever joins is player two.
+pub const HOST: usize = 0;
+pub const GUEST: usize = 1;
+
+const MAX_MESSAGE_LEN: usize = 1 << 20;
+// Frames between hashes of the game being sent.
+const STATE_INTERVAL: u64 = 60;
+// How long anyone connecting to the host gets to answer its hello.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    // Sent by both sides on connecting. The host's picks the mode, seed and
+    // garbage rules, and the guest's repeats them back.
+    Hello { version: u32, mode: Mode, seed: u64, garbage: GarbageRules },
+    // A spectator's answer to the host's hello.
+    Watch { version: u32 },
+    // One of the players' messages, passed on to spectators by the host.
+    Relay { player: usize, message: Box<Message> },
+    // A frame of the sender's game: the garbage it took in just before the
+    // frame, then the command it ticked with.
+    Input { frame: u64, command: Command, garbage: u32 },
+    // Garbage sent to the receiver.
+    Attack { frame: u64, lines: u32 },
+    // A hash of the sender's game as of the end of the frame.
+    State { frame: u64, hash: u64 },
+    // The sender is leaving.
+    Bye,
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Message) -> io::Result<()> {
+    let json = serde_json::to_vec(message)?;
+    writer.write_all(&(json.len() as u32).to_be_bytes())?;
+    writer.write_all(&json)?;
+    writer.flush()
+}
+
+// Reads the next message. None if the connection was closed between
+// messages.
+pub fn read_message(reader: &mut impl Read) -> io::Result<Option<Message>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => (),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_MESSAGE_LEN {
+        return Err(protocol_error(&format!("message of {len} bytes is too long")));
+    }
+    let mut json = vec![0; len];
+    reader.read_exact(&mut json)?;
+    Ok(Some(serde_json::from_slice(&json)?))
+}
+
+// A connection to the other side. Messages are read on a thread of their
+// own, so they can be picked up between frames without waiting.
+pub struct Connection {
+    writer: TcpStream,
+    messages: Receiver<io::Result<Message>>,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || loop {
+            let message = match read_message(&mut reader) {
+                Ok(Some(message)) => Ok(message),
+                Ok(None) => return,
+                Err(err) => Err(err),
+            };
+            let failed = message.is_err();
+            if sender.send(message).is_err() || failed {
+                return;
+            }
+        });
+        Ok(Self { writer: stream, messages })
+    }
+
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        write_message(&mut self.writer, message)
+    }
+
+    // Waits for the next message.
+    pub fn receive(&self) -> io::Result<Message> {
+        self.messages.recv().map_err(|_| disconnected())?
+    }
+
+    // The next message if one has arrived. Fails once the connection is
+    // closed and every message has been read.
+    pub fn try_receive(&self) -> io::Result<Option<Message>> {
+        match self.messages.try_recv() {
+            Ok(message) => message.map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(disconnected()),
+        }
+    }
+}
+
+// Waits for someone to join on the port, then starts a match with them. Any
+// spectators that turn up first are kept to watch, as are any that come
+// later.
+pub fn host(port: u16, mode: Mode, seed: u64, garbage: GarbageRules) -> io::Result<NetMatch> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    let hello = Message::Hello { version: PROTOCOL_VERSION, mode: mode.clone(), seed, garbage };
+    let mut spectators = Vec::new();
+    let connection = loop {
+        let (mut stream, _) = listener.accept()?;
+        // Anyone who doesn't answer properly is dropped, and the host
+        // carries on waiting for a player.
+        match greet(&mut stream, &hello) {
+            Ok(Message::Hello { .. }) => break Connection::new(stream)?,
+            Ok(_) => spectators.push(Spectator::start(stream, None)),
+            Err(_) => (),
+        }
+    };
+    listener.set_nonblocking(true)?;
+    let mut net = NetMatch::new(connection, HOST, Versus::with_rules(mode, seed, garbage));
+    net.listener = Some(listener);
+    net.hello = Some(hello);
+    net.spectators = spectators;
+    Ok(net)
+}
+
+// Joins a match hosted at the address, playing whatever mode the host chose.
+pub fn join(addr: &str) -> io::Result<NetMatch> {
+    let mut connection = Connection::new(TcpStream::connect(addr)?)?;
+    let hello = connection.receive()?;
+    let Message::Hello { version, mode, seed, garbage } = hello.clone() else {
+        return Err(protocol_error(&format!("expected hello, got {hello:?}")));
+    };
+    check_version(version)?;
+    connection.send(&hello)?;
+    Ok(NetMatch::new(connection, GUEST, Versus::with_rules(mode, seed, garbage)))
+}
+
+// Connects to a hosted match to watch it.
+pub fn watch(addr: &str) -> io::Result<Spectating> {
+    let mut connection = Connection::new(TcpStream::connect(addr)?)?;
+    let (mode, seed, garbage) = match connection.receive()? {
+        Message::Hello { version, mode, seed, garbage } => {
+            check_version(version)?;
+            (mode, seed, garbage)
+        }
+        other => return Err(protocol_error(&format!("expected hello, got {other:?}"))),
+    };
+    connection.send(&Message::Watch { version: PROTOCOL_VERSION })?;
+    Ok(Spectating {
+        connection,
+        versus: Versus::with_rules(mode, seed, garbage),
+        replicas: Default::default(),
+        left: [false; PLAYERS],
+        disconnected: false,
+    })
+}
+
+// A spectator's connection, as seen by the host. Messages are written on a
+// thread of their own so a slow spectator can't hold up the game.
+struct Spectator {
+    sender: Sender<Message>,
+}
+
+impl Spectator {
+    // Starts relaying to a spectator, first greeting it with the hello if it
+    // hasn't been greeted yet.
+    fn start(mut stream: TcpStream, hello: Option<Message>) -> Self {
+        let (sender, messages) = mpsc::channel::<Message>();
+        thread::spawn(move || {
+            if let Some(hello) = hello {
+                if !matches!(greet(&mut stream, &hello), Ok(Message::Watch { .. })) {
+                    return;
+                }
+            }
+            for message in messages {
+                if write_message(&mut stream, &message).is_err() {
+                    return;
+                }
+            }
+        });
+        Self { sender }
+    }
+
+    // False once the spectator has gone.
+    fn send(&self, message: Message) -> bool {
+        self.sender.send(message).is_ok()
+    }
+}
+
+// Says hello to someone who's connected to the host, and returns their
+// answer: a hello from a player, or a watch from a spectator.
+fn greet(stream: &mut TcpStream, hello: &Message) -> io::Result<Message> {
+    stream.set_nodelay(true)?;
+    write_message(stream, hello)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let answer = read_message(stream)?;
+    stream.set_read_timeout(None)?;
+    match answer {
+        Some(answer @ (Message::Hello { version, .. } | Message::Watch { version })) => {
+            check_version(version)?;
+            Ok(answer)
+        }
+        other => Err(protocol_error(&format!("expected hello or watch, got {other:?}"))),
+    }
+}
+
+// Everything relayed so far, for catching up spectators that come late. Only
+// the players' inputs matter to a replica, so that's all that's kept, with
+// the commands run-length encoded and garbage noted by the frame it came in.
+#[derive(Default)]
+struct History {
+    inputs: [Inputs; PLAYERS],
+    garbage: [Vec<(u64, u32)>; PLAYERS],
+    left: [bool; PLAYERS],
+}
+
+impl History {
+    fn record(&mut self, player: usize, message: &Message) {
+        match *message {
+            Message::Input { frame, command, garbage } => {
+                self.inputs[player].record(command);
+                if garbage > 0 {
+                    self.garbage[player].push((frame, garbage));
+                }
+            }
+            Message::Bye => self.left[player] = true,
+            _ => (),
+        }
+    }
+
+    // The relays that bring a replica up to date, one player at a time.
+    fn messages(&self) -> impl Iterator<Item = Message> + '_ {
+        (0..PLAYERS).flat_map(move |player| {
+            let mut garbage = self.garbage[player].iter().peekable();
+            let inputs = self.inputs[player].commands().zip(1..).map(move |(command, frame)| {
+                let garbage = garbage.next_if(|&&(at, _)| at == frame).map_or(0, |&(_, lines)| lines);
+                Message::Input { frame, command, garbage }
+            });
+            inputs
+                .chain(self.left[player].then_some(Message::Bye))
+                .map(move |message| Message::Relay { player, message: Box::new(message) })
+        })
+    }
+}
+
+// A copy of someone else's game, kept in step by their messages.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Replica {
+    // The last frame copied.
+    frame: u64,
+    // The frame the copy was first found to differ from the real game.
+    desync: Option<u64>,
+}
+
+impl Replica {
+    // Applies one of the game's messages to the copy.
+    fn apply(&mut self, game: &mut Game, message: &Message) {
+        match *message {
+            Message::Input { frame, command, garbage } => {
+                game.receive_garbage(garbage);
+                game.tick(command);
+                // Attacks are passed on by messages of their own.
+                game.take_attack();
+                self.frame = frame;
+            }
+            Message::State { frame, hash } => {
+                let in_sync = frame == self.frame && hash == game.state_hash();
+                if !in_sync && self.desync.is_none() {
+                    self.desync = Some(frame);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+// A versus match with the other player across the network. The local game
+// is ticked here, while the opponent's is a copy driven by their messages,
+// so it runs a little behind.
+pub struct NetMatch {
+    connection: Connection,
+    versus: Versus,
+    // Which of the games is played here.
+    player: usize,
+    frame: u64,
+    opponent: Replica,
+    // Garbage from the opponent that hasn't been passed to the local game.
+    garbage: u32,
+    disconnected: bool,
+    // On the host, where spectators connect, what they're greeted with,
+    // and what's been relayed to them so far for those that come late.
+    listener: Option<TcpListener>,
+    hello: Option<Message>,
+    spectators: Vec<Spectator>,
+    history: History,
+}
+
+impl NetMatch {
+    fn new(connection: Connection, player: usize, versus: Versus) -> Self {
+        Self {
+            connection,
+            versus,
+            player,
+            frame: 0,
+            opponent: Replica::default(),
+            garbage: 0,
+            disconnected: false,
+            listener: None,
+            hello: None,
+            spectators: Vec::new(),
+            history: History::default(),
+        }
+    }
+
+    pub fn versus(&self) -> &Versus {
+        &self.versus
+    }
+
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    pub fn game(&self) -> &Game {
+        self.versus.game(self.player)
+    }
+
+    pub fn desync(&self) -> Option<u64> {
+        self.opponent.desync
+    }
+
+    pub fn spectators(&self) -> usize {
+        self.spectators.len()
+    }
+
+    pub fn is_disconnected(&self) -> bool {
+        self.disconnected
+    }
+
+    // Whether there's nothing left to play, either because the match was
+    // decided or the opponent's gone.
+    pub fn is_over(&self) -> bool {
+        self.versus.is_over() || self.disconnected
+    }
+
+    // What's gone wrong with the connection, if anything.
+    pub fn status(&self) -> String {
+        match (self.disconnected, self.opponent.desync) {
+            (true, _) => String::from("Opponent left"),
+            (false, Some(frame)) => format!("Out of sync since frame {frame}!"),
+            (false, None) => String::new(),
+        }
+    }
+
+    // Runs a frame of the local game and tells the opponent about it.
+    pub fn tick(&mut self, command: Command) {
+        if self.is_over() {
+            return;
+        }
+        let garbage = std::mem::take(&mut self.garbage);
+        let game = self.versus.game_mut(self.player);
+        game.receive_garbage(garbage);
+        game.tick(command);
+        self.frame += 1;
+        let attack = game.take_attack();
+
+        let frame = self.frame;
+        self.send(Message::Input { frame, command, garbage });
+        if attack > 0 {
+            self.send(Message::Attack { frame, lines: attack });
+        }
+        if frame.is_multiple_of(STATE_INTERVAL) {
+            let hash = self.game().state_hash();
+            self.send(Message::State { frame, hash });
+        }
+    }
+
+    // Handles every message that's arrived from the opponent, and lets in
+    // any new spectators.
+    pub fn poll(&mut self) -> io::Result<()> {
+        self.accept_spectators()?;
+        while !self.disconnected {
+            match self.connection.try_receive() {
+                Ok(Some(message)) => self.handle(message)?,
+                Ok(None) => break,
+                Err(_) => self.disconnected = true,
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, message: Message) -> io::Result<()> {
+        let opponent = versus::opponent(self.player);
+        match message {
+            Message::Input { .. } | Message::State { .. } => {
+                self.opponent.apply(self.versus.game_mut(opponent), &message);
+            }
+            Message::Attack { lines, .. } => self.garbage += lines,
+            Message::Bye => self.disconnected = true,
+            _ => return Err(protocol_error(&format!("unexpected {message:?}"))),
+        }
+        self.relay(opponent, message);
+        Ok(())
+    }
+
+    fn accept_spectators(&mut self) -> io::Result<()> {
+        let (Some(listener), Some(hello)) = (&self.listener, &self.hello) else {
+            return Ok(());
+        };
+        loop {
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(err) => return Err(err),
+            };
+            stream.set_nonblocking(false)?;
+            let spectator = Spectator::start(stream, Some(hello.clone()));
+            for message in self.history.messages() {
+                spectator.send(message);
+            }
+            self.spectators.push(spectator);
+        }
+    }
+
+    // Passes a player's message on to the spectators, if this is the host.
+    fn relay(&mut self, player: usize, message: Message) {
+        if self.listener.is_none() {
+            return;
+        }
+        self.history.record(player, &message);
+        let relayed = Message::Relay { player, message: Box::new(message) };
+        self.spectators.retain(|spectator| spectator.send(relayed.clone()));
+    }
+
+    // A failed send means the opponent's gone, which shows up in the status.
+    fn send(&mut self, message: Message) {
+        if self.connection.send(&message).is_err() {
+            self.disconnected = true;
+        }
+        self.relay(self.player, message);
+    }
+}
+
+// Watching a hosted match. Both games are replicas, driven by the players'
+// messages as the host relays them.
+pub struct Spectating {
+    connection: Connection,
+    versus: Versus,
+    replicas: [Replica; PLAYERS],
+    // Which players have left.
+    left: [bool; PLAYERS],
+    disconnected: bool,
+}
+
+impl Spectating {
+    pub fn versus(&self) -> &Versus {
+        &self.versus
+    }
+
+    // Handles every message that's arrived from the host.
+    pub fn poll(&mut self) -> io::Result<()> {
+        while !self.disconnected {
+            match self.connection.try_receive() {
+                Ok(Some(Message::Relay { player, message })) if player < PLAYERS => {
+                    if *message == Message::Bye {
+                        self.left[player] = true;
+                    }
+                    self.replicas[player].apply(self.versus.game_mut(player), &message);
+                }
+                Ok(Some(message)) => {
+                    return Err(protocol_error(&format!("unexpected {message:?}")));
+                }
+                Ok(None) => break,
+                Err(_) => self.disconnected = true,
+            }
+        }
+        Ok(())
+    }
+
+    // Who's left and whether either game has drifted, if anything's wrong.
+    pub fn status(&self) -> String {
+        let mut problems = Vec::new();
+        if self.disconnected {
+            problems.push(String::from("Host left"));
+        }
+        for player in 0..PLAYERS {
+            if self.left[player] && !self.disconnected {
+                problems.push(format!("Player {} left", player + 1));
+            }
+            if let Some(frame) = self.replicas[player].desync {
+                problems.push(format!("Player {} out of sync since frame {frame}!", player + 1));
+            }
+        }
+        problems.join(", ")
+    }
+}
+
+impl Drop for NetMatch {
+    fn drop(&mut self) {
+        if !self.disconnected {
+            let _ = self.connection.send(&Message::Bye);
+        }
+        self.relay(self.player, Message::Bye);
+    }
+}
+
+fn check_version(version: u32) -> io::Result<()> {
+    if version == PROTOCOL_VERSION {
+        Ok(())
+    } else {
+        Err(protocol_error(&format!(
+            "the other side speaks protocol version {version}, not {PROTOCOL_VERSION}"
+        )))
+    }
+}
+
+fn disconnected() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionAborted, "the other side disconnected")
+}
+
+fn protocol_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
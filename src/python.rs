@@ -0,0 +1,309 @@
+// Python bindings, built as the `tetris` extension module. Boards come out
+// as numpy arrays of cell codes, indexed [row, column] from the top left,
+// with the codes listed in the module's CELLS.
+
+use numpy::{PyArray1, PyArray2, PyArrayMethods};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::bot::Features;
+use crate::env;
+use crate::game::{self, Board, Cell, Command, HEIGHT, WIDTH};
+use crate::mode::{Dig, Invisible, Marathon, Mode, Zen};
+use crate::movegen;
+use crate::randomizer::Randomizer;
+use crate::tetromino::{PositionedTetromino, Tetromino};
+
+// In the order of their codes.
+const CELLS: [&str; 17] = [
+    "empty", "i", "j", "l", "o", "s", "t", "z", "garbage",
+    "i_ghost", "j_ghost", "l_ghost", "o_ghost", "s_ghost", "t_ghost", "z_ghost", "target",
+];
+
+const COMMANDS: [(&str, Command); 11] = [
+    ("left", Command::Left),
+    ("right", Command::Right),
+    ("drop", Command::Drop),
+    ("hard_drop", Command::HardDrop),
+    ("up", Command::Up),
+    ("counter_clockwise", Command::CounterClockwise),
+    ("clockwise", Command::Clockwise),
+    ("change_piece", Command::ChangePiece),
+    ("undo", Command::Undo),
+    ("redo", Command::Redo),
+    ("noop", Command::NoOp),
+];
+
+#[pyclass(name = "Game", module = "tetris")]
+struct PyGame {
+    game: game::Game,
+}
+
+#[pymethods]
+impl PyGame {
+    #[new]
+    #[pyo3(signature = (mode = "marathon", seed = 0, randomizer = "classic"))]
+    fn new(mode: &str, seed: u64, randomizer: &str) -> PyResult<Self> {
+        let mode = parse_mode(mode)?;
+        let randomizer = Randomizer::from_name(randomizer)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown randomizer: {randomizer}")))?;
+        Ok(Self { game: game::Game::with_randomizer(mode, seed, randomizer) })
+    }
+
+    // Runs one frame with the named command.
+    #[pyo3(signature = (command = "noop"))]
+    fn tick(&mut self, command: &str) -> PyResult<()> {
+        self.game.tick(parse_command(command)?);
+        Ok(())
+    }
+
+    // Puts the active piece in one of `placements()`, then runs on until the
+    // next piece is ready to move. Returns where the piece locked, which can
+    // differ from the placement if gravity carried it out of reach.
+    fn place(&mut self, placement: &PyPlacement) -> Option<PyPiece> {
+        env::place(&mut self.game, placement.placement.clone()).as_ref().map(PyPiece::of)
+    }
+
+    // Where the active piece can go. Empty between pieces.
+    fn placements(&self) -> Vec<PyPlacement> {
+        match self.game.active_piece() {
+            Some(active_piece) if !self.game.is_over() => {
+                movegen::placements_from(active_piece, self.game.board())
+                    .into_iter()
+                    .map(|placement| PyPlacement { placement })
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    // The board as drawn on screen: the stack, ghost and active piece.
+    fn render_board<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<u8>>> {
+        board_array(py, &self.game.render_board())
+    }
+
+    // Just the locked-in stack.
+    fn board<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<u8>>> {
+        board_array(py, self.game.board())
+    }
+
+    // The column heights of the stack, left to right.
+    fn heights(&self) -> Vec<u32> {
+        let board = self.game.board();
+        (0..WIDTH as usize)
+            .map(|x| {
+                (0..HEIGHT as usize)
+                    .find(|&y| board[y][x] != Cell::Empty)
+                    .map_or(0, |y| HEIGHT as u32 - y as u32)
+            })
+            .collect()
+    }
+
+    // How many empty cells have a block somewhere above them.
+    fn holes(&self) -> u32 {
+        Features::of(self.game.board()).holes
+    }
+
+    #[getter]
+    fn active_piece(&self) -> Option<PyPiece> {
+        self.game.active_piece().map(PyPiece::of)
+    }
+
+    #[getter]
+    fn next_piece(&self) -> &'static str {
+        letter(self.game.next_piece())
+    }
+
+    #[getter]
+    fn score(&self) -> i32 {
+        self.game.render_score()
+    }
+
+    #[getter]
+    fn level(&self) -> u32 {
+        self.game.render_level()
+    }
+
+    #[getter]
+    fn lines(&self) -> u32 {
+        self.game.render_lines()
+    }
+
+    #[getter]
+    fn pieces(&self) -> u32 {
+        self.game.render_pieces()
+    }
+
+    #[getter]
+    fn frame(&self) -> u64 {
+        self.game.frame()
+    }
+
+    #[getter]
+    fn seed(&self) -> u64 {
+        self.game.seed()
+    }
+
+    #[getter]
+    fn message(&self) -> &str {
+        self.game.render_message()
+    }
+
+    #[getter]
+    fn is_over(&self) -> bool {
+        self.game.is_over()
+    }
+
+    #[getter]
+    fn is_complete(&self) -> bool {
+        self.game.is_complete()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Game(mode={:?}, seed={}, score={}, lines={}, pieces={})",
+            self.game.mode().name(),
+            self.game.seed(),
+            self.game.render_score(),
+            self.game.render_lines(),
+            self.game.render_pieces(),
+        )
+    }
+}
+
+// A piece somewhere on the board.
+#[pyclass(name = "Piece", module = "tetris", frozen)]
+struct PyPiece {
+    #[pyo3(get)]
+    kind: &'static str,
+    #[pyo3(get)]
+    rotation: u8,
+    // (row, column) of each block.
+    #[pyo3(get)]
+    cells: Vec<(i32, i32)>,
+}
+
+impl PyPiece {
+    fn of(piece: &PositionedTetromino) -> Self {
+        Self {
+            kind: letter(piece.tetromino()),
+            rotation: rotation(piece.tetromino()),
+            cells: piece.get_coords(),
+        }
+    }
+}
+
+#[pymethods]
+impl PyPiece {
+    fn __repr__(&self) -> String {
+        format!("Piece(kind={:?}, rotation={}, cells={:?})", self.kind, self.rotation, self.cells)
+    }
+}
+
+// A spot the active piece can lock in, as listed by `Game.placements()`.
+#[pyclass(name = "Placement", module = "tetris", frozen)]
+struct PyPlacement {
+    placement: movegen::Placement,
+}
+
+#[pymethods]
+impl PyPlacement {
+    #[getter]
+    fn piece(&self) -> PyPiece {
+        PyPiece::of(&self.placement.piece)
+    }
+
+    // The command names that get the piece there, ending in a hard drop.
+    #[getter]
+    fn path(&self) -> Vec<&'static str> {
+        self.placement.path.iter().map(|&command| command_name(command)).collect()
+    }
+
+    #[getter]
+    fn t_spin(&self) -> bool {
+        self.placement.t_spin
+    }
+
+    // The stack with the piece locked in, before any lines are cleared.
+    fn apply<'py>(&self, py: Python<'py>, game: &PyGame) -> PyResult<Bound<'py, PyArray2<u8>>> {
+        board_array(py, &self.placement.apply(game.game.board()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Placement(piece={}, path={:?}, t_spin={})",
+            self.piece().__repr__(),
+            self.path(),
+            self.t_spin(),
+        )
+    }
+}
+
+// Public so the interpreter can be given the module when it's embedded, as
+// the tests do.
+#[pymodule]
+pub fn tetris(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyGame>()?;
+    module.add_class::<PyPiece>()?;
+    module.add_class::<PyPlacement>()?;
+    module.add("WIDTH", WIDTH)?;
+    module.add("HEIGHT", HEIGHT)?;
+    module.add("CELLS", CELLS.to_vec())?;
+    module.add("COMMANDS", COMMANDS.map(|(name, _)| name).to_vec())?;
+    Ok(())
+}
+
+fn board_array<'py>(py: Python<'py>, board: &Board) -> PyResult<Bound<'py, PyArray2<u8>>> {
+    let cells: Vec<u8> = board.iter().flatten().map(|&cell| cell as u8).collect();
+    PyArray1::from_vec(py, cells).reshape([HEIGHT as usize, WIDTH as usize])
+}
+
+fn parse_mode(name: &str) -> PyResult<Mode> {
+    Ok(match name {
+        "marathon" => Mode::Marathon(Marathon::default()),
+        "dig" => Mode::Dig(Dig::default()),
+        "master" => Mode::Master,
+        "zen" => Mode::Zen(Zen::default()),
+        "invisible" => Mode::Invisible(Invisible::default()),
+        "finesse" => Mode::Finesse,
+        _ => return Err(PyValueError::new_err(format!("Unknown mode: {name}"))),
+    })
+}
+
+fn parse_command(name: &str) -> PyResult<Command> {
+    COMMANDS.iter()
+        .find(|(command_name, _)| *command_name == name)
+        .map(|&(_, command)| command)
+        .ok_or_else(|| PyValueError::new_err(format!("Unknown command: {name}")))
+}
+
+fn command_name(command: Command) -> &'static str {
+    COMMANDS.iter()
+        .find(|(_, other)| *other == command)
+        .map(|&(name, _)| name)
+        .expect("every command has a name")
+}
+
+fn letter(tetromino: &Tetromino) -> &'static str {
+    match tetromino {
+        Tetromino::I(_) => "I",
+        Tetromino::J(_) => "J",
+        Tetromino::L(_) => "L",
+        Tetromino::O => "O",
+        Tetromino::S(_) => "S",
+        Tetromino::T(_) => "T",
+        Tetromino::Z(_) => "Z",
+    }
+}
+
+fn rotation(tetromino: &Tetromino) -> u8 {
+    match tetromino {
+        Tetromino::I(rotation)
+        | Tetromino::J(rotation)
+        | Tetromino::L(rotation)
+        | Tetromino::S(rotation)
+        | Tetromino::T(rotation)
+        | Tetromino::Z(rotation) => *rotation,
+        Tetromino::O => 0,
+    }
+}
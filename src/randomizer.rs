@@ -0,0 +1,83 @@
+use std::collections::VecDeque;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::tetromino::Tetromino;
+
+const PIECES: [Tetromino; 7] = [
+    Tetromino::I(0),
+    Tetromino::J(0),
+    Tetromino::L(0),
+    Tetromino::O,
+    Tetromino::S(0),
+    Tetromino::T(0),
+    Tetromino::Z(0),
+];
+
+// How the order of pieces is picked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Randomizer {
+    // Any piece but the one just dealt.
+    #[default]
+    Classic,
+    // Every run of seven pieces has one of each, shuffled.
+    SevenBag,
+}
+
+impl Randomizer {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Randomizer::Classic => "classic",
+            Randomizer::SevenBag => "bag",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Randomizer::Classic),
+            "bag" => Some(Randomizer::SevenBag),
+            _ => None,
+        }
+    }
+}
+
+// Deals out pieces under a randomizer, remembering what's left in the bag.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Dealer {
+    randomizer: Randomizer,
+    bag: Vec<Tetromino>,
+    // Pieces to deal, in order, before the randomizer gets a say.
+    queue: VecDeque<Tetromino>,
+}
+
+impl Dealer {
+    pub fn new(randomizer: Randomizer) -> Self {
+        Self::with_queue(randomizer, Vec::new())
+    }
+
+    pub fn with_queue(randomizer: Randomizer, queue: Vec<Tetromino>) -> Self {
+        Self { randomizer, bag: Vec::new(), queue: queue.into() }
+    }
+
+    pub fn randomizer(&self) -> Randomizer {
+        self.randomizer
+    }
+
+    pub fn deal(&mut self, prev_piece: Option<&Tetromino>, rng: &mut impl Rng) -> Tetromino {
+        if let Some(piece) = self.queue.pop_front() {
+            return piece;
+        }
+        match self.randomizer {
+            Randomizer::Classic => Tetromino::new(prev_piece, rng),
+            Randomizer::SevenBag => {
+                if self.bag.is_empty() {
+                    self.bag = PIECES.to_vec();
+                    self.bag.shuffle(rng);
+                }
+                self.bag.pop().expect("the bag was just refilled")
+            }
+        }
+    }
+}
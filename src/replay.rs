@@ -0,0 +1,195 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Command, Game, Setup};
+use crate::garbage::GarbageRules;
+use crate::mode::Mode;
+use crate::randomizer::Randomizer;
+use crate::storage;
+use crate::versus::{PLAYERS, Versus};
+
+// Bump this whenever a change to the engine would make old replays play out
+// differently.
+pub const REPLAY_VERSION: u32 = 1;
+
+// The command given on every frame. Runs of the same command are stored as
+// (command, count) pairs, since most frames are NoOps.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Inputs(Vec<(Command, u32)>);
+
+impl Inputs {
+    pub fn record(&mut self, command: Command) {
+        match self.0.last_mut() {
+            Some((last, count)) if *last == command => *count += 1,
+            _ => self.0.push((command, 1)),
+        }
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.0.iter().map(|(_, count)| count).sum()
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = Command> + '_ {
+        self.0.iter()
+            .flat_map(|(command, count)| std::iter::repeat_n(*command, *count as usize))
+    }
+}
+
+// Everything needed to replay a game: its seed, mode and randomizer, plus the
+// inputs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    version: u32,
+    seed: u64,
+    mode: Mode,
+    // Replays from before there was a choice of randomizer used the classic one.
+    #[serde(default)]
+    randomizer: Randomizer,
+    // Where the game started from, if not an empty board.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    setup: Option<Setup>,
+    inputs: Inputs,
+}
+
+impl Replay {
+    pub fn for_game(game: &Game) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            seed: game.seed(),
+            mode: game.mode().clone(),
+            randomizer: game.randomizer(),
+            setup: game.setup().cloned(),
+            inputs: Inputs::default(),
+        }
+    }
+
+    pub fn record(&mut self, command: Command) {
+        self.inputs.record(command);
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.inputs.frames()
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = Command> + '_ {
+        self.inputs.commands()
+    }
+
+    pub fn new_game(&self) -> Game {
+        match &self.setup {
+            Some(setup) => {
+                Game::with_setup(self.mode.clone(), self.seed, self.randomizer, setup.clone())
+            }
+            None => Game::with_randomizer(self.mode.clone(), self.seed, self.randomizer),
+        }
+    }
+
+    pub fn default_path() -> PathBuf {
+        storage::data_dir()
+            .join("replays")
+            .join(format!("replay-{}.json", storage::unix_time()))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        save(path, self)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let replay: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        check_version(replay.version)?;
+        Ok(replay)
+    }
+}
+
+// A versus match, with both players' inputs. The garbage they sent each other
+// isn't stored, since playing the inputs back together sends it again.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VersusReplay {
+    version: u32,
+    seed: u64,
+    mode: Mode,
+    garbage: GarbageRules,
+    names: [String; PLAYERS],
+    players: [Inputs; PLAYERS],
+}
+
+impl VersusReplay {
+    pub fn new(mode: Mode, seed: u64, garbage: GarbageRules, names: [String; PLAYERS]) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            seed,
+            mode,
+            garbage,
+            names,
+            players: Default::default(),
+        }
+    }
+
+    pub fn record(&mut self, commands: [Command; PLAYERS]) {
+        for (inputs, command) in self.players.iter_mut().zip(commands) {
+            inputs.record(command);
+        }
+    }
+
+    pub fn names(&self) -> &[String; PLAYERS] {
+        &self.names
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.players[0].frames()
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = [Command; PLAYERS]> + '_ {
+        let [first, second] = &self.players;
+        first.commands().zip(second.commands()).map(|(first, second)| [first, second])
+    }
+
+    pub fn new_versus(&self) -> Versus {
+        Versus::with_rules(self.mode.clone(), self.seed, self.garbage)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        save(path, self)
+    }
+}
+
+// Either kind of replay, as read from a file that could hold one or the
+// other.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Recording {
+    Versus(VersusReplay),
+    Single(Replay),
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let recording: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        check_version(match &recording {
+            Recording::Versus(replay) => replay.version,
+            Recording::Single(replay) => replay.version,
+        })?;
+        Ok(recording)
+    }
+}
+
+fn save(path: &Path, replay: &impl Serialize) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string(replay)?)
+}
+
+fn check_version(version: u32) -> io::Result<()> {
+    if version != REPLAY_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unsupported replay version {version}"),
+        ));
+    }
+    Ok(())
+}
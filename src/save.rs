@@ -0,0 +1,54 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::Game;
+use crate::replay::Replay;
+use crate::storage;
+
+// Bump this whenever `Game` changes shape, so old saves are rejected rather
+// than misread.
+pub const SAVE_VERSION: u32 = 9;
+
+// An in-progress game, along with the replay recorded so far so that the
+// recording can carry on once the game is resumed.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedGame {
+    version: u32,
+    pub game: Game,
+    pub replay: Replay,
+}
+
+impl SavedGame {
+    pub fn new(game: Game, replay: Replay) -> Self {
+        Self {
+            version: SAVE_VERSION,
+            game,
+            replay,
+        }
+    }
+
+    pub fn default_path() -> PathBuf {
+        storage::data_dir().join("save.json")
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let saved: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if saved.version != SAVE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported save version {}", saved.version),
+            ));
+        }
+        Ok(saved)
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::Game;
+use crate::storage;
+
+pub const MAX_ENTRIES: usize = 10;
+pub const MAX_NAME_LEN: usize = 3;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub name: String,
+    pub score: i32,
+    pub time_ms: u64,
+    pub lines: u32,
+    pub level: u32,
+    pub date: u64,
+    pub seed: u64,
+}
+
+impl ScoreEntry {
+    fn for_game(game: &Game, name: &str) -> Self {
+        Self {
+            name: name.chars().take(MAX_NAME_LEN).collect::<String>().to_uppercase(),
+            score: game.render_score(),
+            time_ms: game.render_time().as_millis() as u64,
+            lines: game.render_lines(),
+            level: game.render_level(),
+            date: storage::unix_time(),
+            seed: game.seed(),
+        }
+    }
+}
+
+// Races are ranked by fastest time, everything else by highest score.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScoreTable {
+    pub race: bool,
+    pub entries: Vec<ScoreEntry>,
+}
+
+impl ScoreTable {
+    fn ranks_above(&self, entry: &ScoreEntry, other: &ScoreEntry) -> bool {
+        if self.race {
+            entry.time_ms < other.time_ms
+        } else {
+            entry.score > other.score
+        }
+    }
+}
+
+// The top ten finished games for each mode, keyed by mode name.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HighScores {
+    tables: BTreeMap<String, ScoreTable>,
+}
+
+impl HighScores {
+    pub fn default_path() -> PathBuf {
+        storage::data_dir().join("highscores.json")
+    }
+
+    // A missing file just means no games have been recorded yet.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn modes(&self) -> impl Iterator<Item = &String> {
+        self.tables.keys()
+    }
+
+    pub fn table(&self, mode_name: &str) -> Option<&ScoreTable> {
+        self.tables.get(mode_name)
+    }
+
+    // Finished games make the table if it isn't full yet or they beat the
+    // last entry. Races only count if they were completed, and games started
+    // from a setup don't count at all, since the stack was built for them.
+    pub fn qualifies(&self, game: &Game) -> bool {
+        if !game.is_over()
+            || (game.mode().is_race() && !game.is_complete())
+            || game.setup().is_some()
+        {
+            return false;
+        }
+        let Some(table) = self.table(&game.mode().name()) else {
+            return true;
+        };
+        let candidate = ScoreEntry::for_game(game, "");
+        table.entries.len() < MAX_ENTRIES
+            || table.entries.last().is_some_and(|last| table.ranks_above(&candidate, last))
+    }
+
+    // Adds the game to its mode's table, returning its rank if it made it.
+    pub fn insert(&mut self, game: &Game, name: &str) -> Option<usize> {
+        if !self.qualifies(game) {
+            return None;
+        }
+        let entry = ScoreEntry::for_game(game, name);
+        let table = self.tables.entry(game.mode().name()).or_insert_with(|| ScoreTable {
+            race: game.mode().is_race(),
+            entries: Vec::new(),
+        });
+        let rank = table.entries.iter()
+            .position(|other| table.ranks_above(&entry, other))
+            .unwrap_or(table.entries.len());
+        table.entries.insert(rank, entry);
+        table.entries.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+}
@@ -0,0 +1,103 @@
+use std::io;
+use std::ops::Range;
+
+use crate::bot::{Bot, Planner};
+use crate::game::Game;
+use crate::mode::Mode;
+use crate::randomizer::Randomizer;
+
+// How a single headless game went.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameResult {
+    pub seed: u64,
+    pub score: i32,
+    pub lines: u32,
+    pub pieces: u32,
+    // Pieces a second of game time, at 60 frames a second, however fast the
+    // game was actually played.
+    pub pps: f64,
+}
+
+// Plays the game with the bot, frame by frame without waiting between them,
+// until it ends or `max_pieces` have been placed.
+pub fn play<P: Planner>(game: &mut Game, planner: &mut P, max_pieces: u32) -> io::Result<GameResult> {
+    let mut bot = Bot::new(planner);
+    while !game.is_over() && game.render_pieces() < max_pieces {
+        let command = bot.next_command(game)?;
+        game.tick(command);
+    }
+    Ok(GameResult {
+        seed: game.seed(),
+        score: game.render_score(),
+        lines: game.render_lines(),
+        pieces: game.render_pieces(),
+        pps: game.pieces_per_second(),
+    })
+}
+
+// Plays one game for each seed in turn.
+pub fn run<P: Planner>(
+    planner: &mut P,
+    mode: &Mode,
+    randomizer: Randomizer,
+    seeds: Range<u64>,
+    max_pieces: u32,
+) -> io::Result<Vec<GameResult>> {
+    seeds
+        .map(|seed| {
+            let mut game = Game::with_randomizer(mode.clone(), seed, randomizer);
+            game.skip_finesse();
+            play(&mut game, planner, max_pieces)
+        })
+        .collect()
+}
+
+// The mean and median of each stat, in the order they're reported.
+pub fn summarize(results: &[GameResult]) -> Vec<(&'static str, f64, f64)> {
+    let column = |stat: fn(&GameResult) -> f64| results.iter().map(stat).collect::<Vec<_>>();
+    [
+        ("Score", column(|result| result.score as f64)),
+        ("Lines", column(|result| result.lines as f64)),
+        ("Pieces", column(|result| result.pieces as f64)),
+        ("Game PPS", column(|result| result.pps)),
+    ]
+    .into_iter()
+    .map(|(name, values)| (name, mean(&values), median(values)))
+    .collect()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+pub fn format_table(summary: &[(&'static str, f64, f64)]) -> String {
+    let mut table = format!("{:<10}{:>12}{:>12}\n", "", "mean", "median");
+    for (name, mean, median) in summary {
+        table.push_str(&format!("{name:<10}{mean:>12.2}{median:>12.2}\n"));
+    }
+    table
+}
+
+pub fn format_csv(summary: &[(&'static str, f64, f64)]) -> String {
+    let mut csv = String::from("stat,mean,median\n");
+    for (name, mean, median) in summary {
+        csv.push_str(&format!("{},{mean},{median}\n", name.to_lowercase().replace(' ', "_")));
+    }
+    csv
+}
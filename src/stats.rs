@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+use crate::attack::Clear;
+
+// Running totals for the stats panel.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub keys: u32,
+    pub attack: u32,
+    pub singles: u32,
+    pub doubles: u32,
+    pub triples: u32,
+    pub tetrises: u32,
+    pub t_spins: u32,
+    pub max_combo: u32,
+    // Inputs spent beyond the fewest needed for each placement.
+    pub finesse_faults: u32,
+}
+
+impl Stats {
+    pub fn record_clear(&mut self, clear: &Clear, attack: u32, combo: u32) {
+        self.attack += attack;
+        self.max_combo = self.max_combo.max(combo);
+        if clear.t_spin && clear.lines > 0 {
+            self.t_spins += 1;
+            return;
+        }
+        match clear.lines {
+            1 => self.singles += 1,
+            2 => self.doubles += 1,
+            3 => self.triples += 1,
+            4 => self.tetrises += 1,
+            _ => (),
+        }
+    }
+}
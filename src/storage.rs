@@ -0,0 +1,37 @@
+use std::env;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Replays, saves and scores live under $TETRIS_DATA_DIR, or ~/.tetris if that
+// isn't set.
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("TETRIS_DATA_DIR") {
+        return PathBuf::from(dir);
+    }
+    let home = env::var_os("HOME").unwrap_or_else(|| ".".into());
+    PathBuf::from(home).join(".tetris")
+}
+
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0)
+}
+
+// Formats a unix timestamp as a YYYY-MM-DD date, in UTC.
+pub fn format_date(unix_time: u64) -> String {
+    // Converts days since the epoch to a civil date, following
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (unix_time / 86400) as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524
+        - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{year:04}-{month:02}-{day:02}")
+}
@@ -0,0 +1,449 @@
+// The Tetris Bot Protocol: newline separated JSON messages between a frontend
+// that runs the game and a bot that decides where pieces go. `serve` makes
+// the built-in bot available to TBP frontends over stdin and stdout, and
+// `TbpClient` lets any TBP bot play a `Game`.
+//
+// This engine has no hold and no rotation kicks. Bots are always told the
+// hold slot is empty, and suggestions that hold or can't be reached here are
+// passed over.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::bot::{self, Heuristic, Planner};
+use crate::game::{Board, Cell, Game, HEIGHT, WIDTH};
+use crate::movegen::Placement;
+use crate::tetromino::{PositionedTetromino, Tetromino};
+
+// TBP boards are 40 rows tall, counted up from the bottom.
+const BOARD_ROWS: usize = 40;
+// How long a bot gets to exit after being told to quit before it's killed.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrontendMessage {
+    Rules {},
+    Start(Start),
+    Suggest,
+    Play {
+        #[serde(rename = "move")]
+        mv: Move,
+    },
+    NewPiece { piece: Piece },
+    Stop,
+    Quit,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotMessage {
+    Info {
+        name: String,
+        version: String,
+        author: String,
+        features: Vec<String>,
+    },
+    Ready,
+    Error { reason: String },
+    Suggestion { moves: Vec<Move> },
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Start {
+    pub hold: Option<Piece>,
+    pub queue: Vec<Piece>,
+    pub combo: u32,
+    pub back_to_back: bool,
+    pub board: Vec<Vec<Option<char>>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Piece { I, O, T, L, J, S, Z }
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Orientation { North, East, South, West }
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Spin { None, Mini, Full }
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Move {
+    pub location: Location,
+    pub spin: Spin,
+}
+
+// Where a piece ends up, by the cell it rotates around, with y counted up
+// from the bottom of the board.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    #[serde(rename = "type")]
+    pub piece: Piece,
+    pub orientation: Orientation,
+    pub x: i32,
+    pub y: i32,
+}
+
+const ORIENTATIONS: [Orientation; 4] =
+    [Orientation::North, Orientation::East, Orientation::South, Orientation::West];
+
+impl Piece {
+    pub fn of(tetromino: &Tetromino) -> Self {
+        match tetromino {
+            Tetromino::I(_) => Piece::I,
+            Tetromino::O => Piece::O,
+            Tetromino::T(_) => Piece::T,
+            Tetromino::L(_) => Piece::L,
+            Tetromino::J(_) => Piece::J,
+            Tetromino::S(_) => Piece::S,
+            Tetromino::Z(_) => Piece::Z,
+        }
+    }
+
+    pub fn tetromino(self) -> Tetromino {
+        match self {
+            Piece::I => Tetromino::I(0),
+            Piece::O => Tetromino::O,
+            Piece::T => Tetromino::T(0),
+            Piece::L => Tetromino::L(0),
+            Piece::J => Tetromino::J(0),
+            Piece::S => Tetromino::S(0),
+            Piece::Z => Tetromino::Z(0),
+        }
+    }
+
+    // The piece's cells relative to the one it rotates around, as (x, y)
+    // with y pointing up, under SRS.
+    fn offsets(self, orientation: Orientation) -> [(i32, i32); 4] {
+        let north = match self {
+            Piece::I => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+            Piece::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+            Piece::T => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+            Piece::L => [(-1, 0), (0, 0), (1, 0), (1, 1)],
+            Piece::J => [(-1, 0), (0, 0), (1, 0), (-1, 1)],
+            Piece::S => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+            Piece::Z => [(-1, 1), (0, 1), (0, 0), (1, 0)],
+        };
+        if self == Piece::O {
+            return north;
+        }
+        north.map(|(x, y)| match orientation {
+            Orientation::North => (x, y),
+            Orientation::East => (y, -x),
+            Orientation::South => (-x, -y),
+            Orientation::West => (-y, x),
+        })
+    }
+}
+
+impl Location {
+    // The board cells the piece covers, as (y, x) from the top left like
+    // the rest of the game.
+    pub fn coords(&self) -> Vec<(i32, i32)> {
+        let mut coords: Vec<(i32, i32)> = self.piece.offsets(self.orientation).iter()
+            .map(|(dx, dy)| (HEIGHT - 1 - (self.y + dy), self.x + dx))
+            .collect();
+        coords.sort();
+        coords
+    }
+
+    // Describes a piece on the board the way TBP does.
+    pub fn of(piece: &PositionedTetromino) -> Self {
+        let tbp_piece = Piece::of(piece.tetromino());
+        let mut coords = piece.get_coords();
+        coords.sort();
+        ORIENTATIONS.iter()
+            .flat_map(|&orientation| coords.iter().map(move |&(y, x)| Location {
+                piece: tbp_piece,
+                orientation,
+                x,
+                y: HEIGHT - 1 - y,
+            }))
+            .find(|location| location.coords() == coords)
+            .expect("every piece has a cell it rotates around")
+    }
+}
+
+impl Move {
+    pub fn of(placement: &Placement) -> Self {
+        Move {
+            location: Location::of(&placement.piece),
+            spin: if placement.t_spin { Spin::Full } else { Spin::None },
+        }
+    }
+
+    // The placement this move describes, out of the ones the piece can
+    // reach. Spun and non-spun ways into the same spot are told apart when
+    // both are possible. There are no mini T-spins here, so a mini counts as
+    // a spin.
+    pub fn find_in(&self, placements: &[Placement]) -> Option<Placement> {
+        let coords = self.location.coords();
+        let matching: Vec<&Placement> = placements.iter()
+            .filter(|placement| {
+                let mut placed = placement.piece.get_coords();
+                placed.sort();
+                placed == coords
+            })
+            .collect();
+        matching.iter()
+            .find(|placement| placement.t_spin == (self.spin != Spin::None))
+            .or(matching.first())
+            .map(|placement| (*placement).clone())
+    }
+}
+
+fn encode_board(board: &Board) -> Vec<Vec<Option<char>>> {
+    let mut rows: Vec<Vec<Option<char>>> = board.iter().rev()
+        .map(|row| row.iter().map(|cell| encode_cell(*cell)).collect())
+        .collect();
+    rows.resize(BOARD_ROWS, vec![None; WIDTH as usize]);
+    rows
+}
+
+fn encode_cell(cell: Cell) -> Option<char> {
+    match cell {
+        Cell::Empty => None,
+        Cell::IBlock => Some('I'),
+        Cell::JBlock => Some('J'),
+        Cell::LBlock => Some('L'),
+        Cell::OBlock => Some('O'),
+        Cell::SBlock => Some('S'),
+        Cell::TBlock => Some('T'),
+        Cell::ZBlock => Some('Z'),
+        _ => Some('G'),
+    }
+}
+
+// Rows above the top of this game's board are dropped.
+fn decode_board(rows: &[Vec<Option<char>>]) -> Board {
+    let mut board = [[Cell::Empty; WIDTH as usize]; HEIGHT as usize];
+    for (row, cells) in board.iter_mut().rev().zip(rows) {
+        for (cell, tbp_cell) in row.iter_mut().zip(cells) {
+            *cell = match tbp_cell {
+                None => Cell::Empty,
+                Some('I') => Cell::IBlock,
+                Some('J') => Cell::JBlock,
+                Some('L') => Cell::LBlock,
+                Some('O') => Cell::OBlock,
+                Some('S') => Cell::SBlock,
+                Some('T') => Cell::TBlock,
+                Some('Z') => Cell::ZBlock,
+                Some(_) => Cell::Garbage,
+            };
+        }
+    }
+    board
+}
+
+fn send<T: Serialize>(writer: &mut impl Write, message: &T) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, message)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+// Reads the next message, or None once the other side hangs up.
+fn receive<T: for<'de> Deserialize<'de>>(reader: &mut impl BufRead) -> io::Result<Option<T>> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if !line.trim().is_empty() {
+            return Ok(Some(serde_json::from_str(&line)?));
+        }
+    }
+}
+
+// Runs the built-in bot as a TBP bot, talking to a frontend over the given
+// reader and writer until it quits.
+pub fn serve(reader: impl BufRead, mut writer: impl Write, heuristic: &Heuristic) -> io::Result<()> {
+    let mut reader = reader;
+    send(&mut writer, &BotMessage::Info {
+        name: String::from(env!("CARGO_PKG_NAME")),
+        version: String::from(env!("CARGO_PKG_VERSION")),
+        author: String::from("tetris"),
+        features: Vec::new(),
+    })?;
+    let mut state: Option<(Board, Vec<Piece>)> = None;
+    while let Some(message) = receive(&mut reader)? {
+        match message {
+            FrontendMessage::Rules {} => send(&mut writer, &BotMessage::Ready)?,
+            FrontendMessage::Start(start) => {
+                state = Some((decode_board(&start.board), start.queue));
+            }
+            FrontendMessage::Suggest => {
+                let moves = match &state {
+                    Some((board, queue)) => suggest(heuristic, board, queue),
+                    None => Vec::new(),
+                };
+                send(&mut writer, &BotMessage::Suggestion { moves })?;
+            }
+            FrontendMessage::Play { mv } => {
+                if let Some((board, queue)) = &mut state {
+                    let cell = mv.location.piece.tetromino().get_cell_type();
+                    for (y, x) in mv.location.coords() {
+                        if (0..HEIGHT).contains(&y) && (0..WIDTH).contains(&x) {
+                            board[y as usize][x as usize] = cell;
+                        }
+                    }
+                    (*board, _) = bot::clear_full_rows(board);
+                    if !queue.is_empty() {
+                        queue.remove(0);
+                    }
+                }
+            }
+            FrontendMessage::NewPiece { piece } => {
+                if let Some((_, queue)) = &mut state {
+                    queue.push(piece);
+                }
+            }
+            FrontendMessage::Stop => state = None,
+            FrontendMessage::Quit => break,
+            FrontendMessage::Unknown => (),
+        }
+    }
+    Ok(())
+}
+
+fn suggest(heuristic: &Heuristic, board: &Board, queue: &[Piece]) -> Vec<Move> {
+    let Some((current, rest)) = queue.split_first() else {
+        return Vec::new();
+    };
+    let Some(start) = PositionedTetromino::place(&current.tetromino(), board) else {
+        return Vec::new();
+    };
+    let rest: Vec<Tetromino> = rest.iter()
+        .take(heuristic.depth())
+        .map(|piece| piece.tetromino())
+        .collect();
+    heuristic.best_placement(board, &start, &rest)
+        .map(|placement| vec![Move::of(&placement)])
+        .unwrap_or_default()
+}
+
+// Lets a TBP bot running as a child process plan where a game's pieces go.
+pub struct TbpClient {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    // The board and piece count the bot expects to see next, if it's been
+    // kept up to date with the game.
+    expected: Option<(Board, u32)>,
+}
+
+impl TbpClient {
+    // Starts the bot with `command` and waits until it's ready to play. The
+    // command is split on whitespace and run directly, not through a shell,
+    // so there's no quoting: a program or argument can't contain spaces.
+    pub fn launch(command: &str) -> io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words.next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty bot command"))?;
+        let mut child = std::process::Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let mut client = Self { child, stdin, stdout, expected: None };
+
+        match client.receive()? {
+            BotMessage::Info { .. } => (),
+            other => return Err(protocol_error(&format!("expected info, got {other:?}"))),
+        }
+        send(&mut client.stdin, &FrontendMessage::Rules {})?;
+        match client.receive()? {
+            BotMessage::Ready => Ok(client),
+            BotMessage::Error { reason } => Err(protocol_error(&reason)),
+            other => Err(protocol_error(&format!("expected ready, got {other:?}"))),
+        }
+    }
+
+    fn receive(&mut self) -> io::Result<BotMessage> {
+        receive(&mut self.stdout)?
+            .ok_or_else(|| protocol_error("bot exited"))
+    }
+
+    // Tells the bot about the game from scratch.
+    fn restart(&mut self, game: &Game) -> io::Result<()> {
+        if self.expected.take().is_some() {
+            send(&mut self.stdin, &FrontendMessage::Stop)?;
+        }
+        let mut queue = Vec::new();
+        if let Some(active_piece) = game.active_piece() {
+            queue.push(Piece::of(active_piece.tetromino()));
+        }
+        queue.push(Piece::of(game.next_piece()));
+        send(&mut self.stdin, &FrontendMessage::Start(Start {
+            hold: None,
+            queue,
+            combo: game.combo(),
+            back_to_back: game.back_to_back(),
+            board: encode_board(game.board()),
+        }))
+    }
+}
+
+impl Planner for TbpClient {
+    fn plan(&mut self, game: &Game, placements: &[Placement]) -> io::Result<Option<Placement>> {
+        let in_sync = self.expected.as_ref()
+            .is_some_and(|(board, pieces)| board == game.board() && *pieces == game.render_pieces());
+        if in_sync {
+            send(&mut self.stdin, &FrontendMessage::NewPiece { piece: Piece::of(game.next_piece()) })?;
+        } else {
+            self.restart(game)?;
+        }
+
+        send(&mut self.stdin, &FrontendMessage::Suggest)?;
+        let moves = loop {
+            match self.receive()? {
+                BotMessage::Suggestion { moves } => break moves,
+                BotMessage::Error { reason } => return Err(protocol_error(&reason)),
+                _ => (),
+            }
+        };
+        let Some(placement) = moves.iter().find_map(|mv| mv.find_in(placements)) else {
+            // Nothing the bot wants is possible here, so it'll need telling
+            // about whatever happens instead.
+            self.expected = None;
+            send(&mut self.stdin, &FrontendMessage::Stop)?;
+            return Ok(None);
+        };
+        send(&mut self.stdin, &FrontendMessage::Play { mv: Move::of(&placement) })?;
+        let (board, _) = bot::lock(game.board(), &placement);
+        self.expected = Some((board, game.render_pieces() + 1));
+        Ok(Some(placement))
+    }
+}
+
+impl Drop for TbpClient {
+    fn drop(&mut self) {
+        let _ = send(&mut self.stdin, &FrontendMessage::Quit);
+        let started = Instant::now();
+        while started.elapsed() < QUIT_TIMEOUT {
+            match self.child.try_wait() {
+                Ok(None) => thread::sleep(Duration::from_millis(10)),
+                _ => return,
+            }
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn protocol_error(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("TBP bot: {reason}"))
+}
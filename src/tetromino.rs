@@ -1,9 +1,11 @@
+use rand::Rng;
 use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 
 use crate::{HEIGHT, WIDTH};
 use crate::game::{Board, Cell, PIECE_START_X, PIECE_START_Y};
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub enum Tetromino {
     I(u8),
     J(u8),
@@ -15,8 +17,8 @@ pub enum Tetromino {
 }
 
 impl Tetromino {
-    pub fn new(prev_piece: Option<&Self>) -> Self {
-        let choices = vec![
+    pub fn new(prev_piece: Option<&Self>, rng: &mut impl Rng) -> Self {
+        let choices = [
             Tetromino::I(0),
             Tetromino::J(0),
             Tetromino::L(0),
@@ -25,18 +27,31 @@ impl Tetromino {
             Tetromino::T(0),
             Tetromino::Z(0),
         ];
-        let mut choice = choices.choose(&mut rand::thread_rng()).unwrap().clone();
+        let mut choice = choices.choose(rng).unwrap().clone();
         match prev_piece {
             None => choice,
             Some(prev) => {
                 while *prev == choice {
-                    choice = choices.choose(&mut rand::thread_rng()).unwrap().clone();
+                    choice = choices.choose(rng).unwrap().clone();
                 }
                 choice
             }
         }
     }
 
+    // The same kind of piece, the way it spawns.
+    pub fn unrotated(&self) -> Self {
+        match self {
+            Tetromino::I(_) => Tetromino::I(0),
+            Tetromino::J(_) => Tetromino::J(0),
+            Tetromino::L(_) => Tetromino::L(0),
+            Tetromino::O => Tetromino::O,
+            Tetromino::S(_) => Tetromino::S(0),
+            Tetromino::T(_) => Tetromino::T(0),
+            Tetromino::Z(_) => Tetromino::Z(0),
+        }
+    }
+
     // Unrotated pieces, shifted so they fit in a 4/2 grid.
     pub fn get_preview_coords(&self) -> Vec<(i32, i32)> {
         match self {
@@ -75,7 +90,7 @@ impl Tetromino {
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub struct PositionedTetromino {
     t: Tetromino,
     y: i32,
@@ -115,7 +130,7 @@ impl PositionedTetromino {
 
     fn is_position_valid(&self, board: &Board) -> bool {
         for (y, x) in self.get_coords() {
-            if y < 0 || y >= HEIGHT || x < 0 || x >= WIDTH
+            if !(0..HEIGHT).contains(&y) || !(0..WIDTH).contains(&x)
                 || board[y as usize][x as usize] != Cell::Empty {
                 return false;
             }
@@ -170,6 +185,12 @@ impl PositionedTetromino {
         tmp.is_position_valid(board)
     }
 
+    pub fn drop_to_floor(&mut self, board: &Board) {
+        while self.can_move_down(board) {
+            self.move_down(board);
+        }
+    }
+
     pub fn move_up(&mut self, board: &Board) {
         self.y -= 1;
         if !self.is_position_valid(board) {
@@ -191,6 +212,22 @@ impl PositionedTetromino {
         }
     }
 
+    // A T piece counts as spun into place if at least three of the four
+    // squares diagonal to its centre are filled or off the board.
+    pub fn is_t_spin_position(&self, board: &Board) -> bool {
+        if !matches!(self.t, Tetromino::T(_)) {
+            return false;
+        }
+        let filled_corners = [(-1, -1), (-1, 1), (1, -1), (1, 1)].iter()
+            .filter(|(dy, dx)| {
+                let (y, x) = (self.y + dy, self.x + dx);
+                !(0..HEIGHT).contains(&y) || !(0..WIDTH).contains(&x)
+                    || board[y as usize][x as usize] != Cell::Empty
+            })
+            .count();
+        filled_corners >= 3
+    }
+
     pub fn get_coords(&self) -> Vec<(i32, i32)> {
         let mut coords: Vec<(i32, i32)> = match self.t {
             Tetromino::I(0) => vec![(0, -1), (0, 0), (0, 1), (0, 2)],
@@ -218,7 +255,11 @@ impl PositionedTetromino {
             *y += self.y;
             *x += self.x;
         }
-        return coords;
+        coords
+    }
+
+    pub fn tetromino(&self) -> &Tetromino {
+        &self.t
     }
 
     pub fn get_cell_type(&self) -> Cell {
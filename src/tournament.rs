@@ -0,0 +1,484 @@
+// Tournaments between bots playing headless versus, as round robins or
+// Swiss systems, with Elo ratings kept from one tournament to the next. Each
+// game is saved as a replay so it can be watched afterwards.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::bot::{Bot, Difficulty, Heuristic, Planner, Weights};
+use crate::garbage::GarbageRules;
+use crate::mode::{FRAMES_PER_SECOND, Mode};
+use crate::replay::VersusReplay;
+use crate::storage;
+use crate::tbp::TbpClient;
+use crate::versus::{PLAYERS, Versus};
+
+// Bump this whenever `Ratings` changes shape, so old tables are rejected
+// rather than misread.
+pub const RATINGS_VERSION: u32 = 1;
+
+const INITIAL_RATING: f64 = 1500.0;
+// The most a rating can move after one game.
+const K_FACTOR: f64 = 32.0;
+// A match is given up on after this many games per win needed, in case the
+// two keep drawing.
+const GAMES_PER_WIN_LIMIT: u32 = 3;
+
+// A bot that can be entered in a tournament, as listed in a bots file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entrant {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: EntrantKind,
+    // The most pieces it may place a second, if it's held back at all.
+    #[serde(default)]
+    pub pps: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EntrantKind {
+    // The built-in bot, with weights saved by tune or the defaults.
+    Heuristic {
+        #[serde(default)]
+        weights: Option<PathBuf>,
+        #[serde(default)]
+        depth: usize,
+    },
+    // An external bot, started with this command and spoken to over TBP.
+    Tbp { command: String },
+}
+
+impl Entrant {
+    // The built-in bot at each difficulty.
+    pub fn built_in() -> Vec<Self> {
+        Difficulty::NAMES.iter()
+            .map(|&name| {
+                let difficulty = Difficulty::from_name(name).expect("every name is a difficulty");
+                Self {
+                    name: name.to_string(),
+                    kind: EntrantKind::Heuristic { weights: None, depth: difficulty.depth },
+                    pps: Some(difficulty.pps).filter(|pps| pps.is_finite()),
+                }
+            })
+            .collect()
+    }
+
+    pub fn load_all(path: &Path) -> io::Result<Vec<Self>> {
+        let entrants: Vec<Self> = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let bad_pps = |pps: f64| !(pps.is_finite() && pps > 0.0);
+        if let Some(entrant) = entrants.iter().find(|entrant| entrant.pps.is_some_and(bad_pps)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}'s pps must be a number above 0", entrant.name),
+            ));
+        }
+        Ok(entrants)
+    }
+
+    // Gets the bot ready to play, starting it up if it's external.
+    fn planner(&self) -> io::Result<Box<dyn Planner>> {
+        Ok(match &self.kind {
+            EntrantKind::Heuristic { weights, depth } => {
+                let weights = match weights {
+                    Some(path) => Weights::load(path)?,
+                    None => Weights::default(),
+                };
+                Box::new(Heuristic::new(weights, *depth))
+            }
+            EntrantKind::Tbp { command } => Box::new(TbpClient::launch(command)?),
+        })
+    }
+
+    fn bot<'a>(&self, planner: &'a mut Box<dyn Planner>) -> Bot<&'a mut Box<dyn Planner>> {
+        let bot = Bot::new(planner);
+        match self.pps {
+            Some(pps) => bot.with_pps_cap(pps),
+            None => bot,
+        }
+    }
+}
+
+// How entrants are paired up.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Format {
+    // Everyone plays everyone else once.
+    #[default]
+    RoundRobin,
+    // Each round pairs entrants on similar scores who haven't met yet.
+    Swiss,
+}
+
+impl Format {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::RoundRobin => "round-robin",
+            Format::Swiss => "swiss",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "round-robin" => Some(Format::RoundRobin),
+            "swiss" => Some(Format::Swiss),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TournamentConfig {
+    pub mode: Mode,
+    pub garbage: GarbageRules,
+    pub format: Format,
+    // Swiss rounds to play. Enough to find a clear winner if not given.
+    pub rounds: Option<u32>,
+    // Games a bot has to win to take a match.
+    pub first_to: u32,
+    // Games still going after this many seconds of play are drawn.
+    pub time_limit: u64,
+    pub seed: u64,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        Self {
+            mode: Mode::default(),
+            garbage: GarbageRules::default(),
+            format: Format::default(),
+            rounds: None,
+            first_to: 3,
+            time_limit: 300,
+            seed: 0,
+        }
+    }
+}
+
+// How a match between two entrants went, by their indices in the entrant
+// list.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchResult {
+    pub round: u32,
+    pub players: [usize; PLAYERS],
+    pub wins: [u32; PLAYERS],
+    pub draws: u32,
+    // Where each game's replay was saved.
+    pub logs: Vec<PathBuf>,
+}
+
+impl MatchResult {
+    pub fn winner(&self) -> Option<usize> {
+        let [first, second] = self.wins;
+        match first.cmp(&second) {
+            std::cmp::Ordering::Greater => Some(self.players[0]),
+            std::cmp::Ordering::Less => Some(self.players[1]),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+}
+
+// An entrant's record in the tournament. Winning a match or having a bye is
+// worth a point, and drawing one half a point.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Standing {
+    pub name: String,
+    pub points: f64,
+    pub matches: u32,
+    pub games_won: u32,
+    pub games_lost: u32,
+    byes: u32,
+}
+
+impl Standing {
+    fn game_difference(&self) -> i64 {
+        self.games_won as i64 - self.games_lost as i64
+    }
+}
+
+// Plays the tournament, saving each game's replay in `logs` and updating the
+// ratings as it goes. Calls `on_match` with the ratings so far after every
+// match, so they can be saved before a later match fails, and returns the
+// final standings, best first.
+pub fn run(
+    entrants: &[Entrant],
+    config: &TournamentConfig,
+    ratings: &mut Ratings,
+    logs: &Path,
+    mut on_match: impl FnMut(&MatchResult, &Ratings) -> io::Result<()>,
+) -> io::Result<Vec<Standing>> {
+    let mut standings: Vec<Standing> = entrants.iter()
+        .map(|entrant| Standing { name: entrant.name.clone(), ..Standing::default() })
+        .collect();
+    let schedule = round_robin(entrants.len());
+    let rounds = match config.format {
+        Format::RoundRobin => schedule.len() as u32,
+        Format::Swiss => config.rounds.unwrap_or_else(|| swiss_rounds(entrants.len())),
+    };
+    let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+    let mut played = HashSet::new();
+    let mut matches = 0;
+    for round in 1..=rounds {
+        let pairs = match config.format {
+            Format::RoundRobin => schedule[round as usize - 1].clone(),
+            Format::Swiss => {
+                let (pairs, bye) = swiss_pairs(&standings, ratings, &played);
+                if let Some(bye) = bye {
+                    standings[bye].points += 1.0;
+                    standings[bye].byes += 1;
+                }
+                pairs
+            }
+        };
+        for (first, second) in pairs {
+            matches += 1;
+            let players = [&entrants[first], &entrants[second]];
+            let mut result = MatchResult {
+                round,
+                players: [first, second],
+                wins: [0; PLAYERS],
+                draws: 0,
+                logs: Vec::new(),
+            };
+            let mut planners = [players[0].planner()?, players[1].planner()?];
+            let game_limit = config.first_to.max(1) * GAMES_PER_WIN_LIMIT;
+            for game in 1..=game_limit {
+                if result.wins.iter().any(|&wins| wins >= config.first_to) {
+                    break;
+                }
+                let (winner, replay) = play_game(players, &mut planners, config, rng.gen())?;
+                let score = match winner {
+                    Some(player) => {
+                        result.wins[player] += 1;
+                        if player == 0 { 1.0 } else { 0.0 }
+                    }
+                    None => {
+                        result.draws += 1;
+                        0.5
+                    }
+                };
+                ratings.record(&players[0].name, &players[1].name, score);
+                let path = logs.join(format!("match-{matches}-game-{game}.json"));
+                replay.save(&path)?;
+                result.logs.push(path);
+            }
+
+            played.insert((first.min(second), first.max(second)));
+            for (player, &entrant) in result.players.iter().enumerate() {
+                let standing = &mut standings[entrant];
+                standing.matches += 1;
+                standing.games_won += result.wins[player];
+                standing.games_lost += result.wins[1 - player];
+                standing.points += match result.winner() {
+                    Some(winner) if winner == entrant => 1.0,
+                    Some(_) => 0.0,
+                    None => 0.5,
+                };
+            }
+            on_match(&result, ratings)?;
+        }
+    }
+    standings.sort_by(|a, b| {
+        b.points.total_cmp(&a.points).then(b.game_difference().cmp(&a.game_difference()))
+    });
+    Ok(standings)
+}
+
+// Plays one headless game of versus between two bots, as fast as it can be
+// worked out. Returns the winner, if there was one, and a replay of the game.
+fn play_game(
+    players: [&Entrant; PLAYERS],
+    planners: &mut [Box<dyn Planner>; PLAYERS],
+    config: &TournamentConfig,
+    seed: u64,
+) -> io::Result<(Option<usize>, VersusReplay)> {
+    let mut versus = Versus::with_rules(config.mode.clone(), seed, config.garbage);
+    for player in 0..PLAYERS {
+        versus.game_mut(player).skip_finesse();
+    }
+    let names = players.map(|entrant| entrant.name.clone());
+    let mut replay = VersusReplay::new(config.mode.clone(), seed, config.garbage, names);
+    let [first, second] = planners;
+    let mut bots = [players[0].bot(first), players[1].bot(second)];
+    let frames = config.time_limit * FRAMES_PER_SECOND as u64;
+    while !versus.is_over() && versus.game(0).frame() < frames {
+        let commands = [
+            bots[0].next_command(versus.game(0))?,
+            bots[1].next_command(versus.game(1))?,
+        ];
+        replay.record(commands);
+        versus.tick(commands);
+    }
+    Ok((versus.winner(), replay))
+}
+
+// Rounds in which everyone plays everyone else once, by the circle method:
+// one seat stays put while the rest move round a place each round. With an
+// odd number of entrants, whoever's across from the empty seat sits out.
+fn round_robin(count: usize) -> Vec<Vec<(usize, usize)>> {
+    let mut seats: Vec<Option<usize>> = (0..count).map(Some).collect();
+    if !seats.len().is_multiple_of(2) {
+        seats.push(None);
+    }
+    let size = seats.len();
+    (1..size)
+        .map(|_| {
+            let round = (0..size / 2)
+                .filter_map(|seat| Some((seats[seat]?, seats[size - 1 - seat]?)))
+                .collect();
+            seats[1..].rotate_right(1);
+            round
+        })
+        .collect()
+}
+
+// Enough Swiss rounds for one entrant to be left unbeaten.
+fn swiss_rounds(count: usize) -> u32 {
+    count.max(1).next_power_of_two().trailing_zeros().max(1)
+}
+
+// Pairs each entrant with the next best placed one they haven't played yet,
+// going down the standings, with ties broken by rating. With an odd number of
+// entrants, the lowest placed of those with the fewest byes gets one.
+fn swiss_pairs(
+    standings: &[Standing],
+    ratings: &Ratings,
+    played: &HashSet<(usize, usize)>,
+) -> (Vec<(usize, usize)>, Option<usize>) {
+    let mut order: Vec<usize> = (0..standings.len()).collect();
+    order.sort_by(|&a, &b| {
+        standings[b].points.total_cmp(&standings[a].points)
+            .then(ratings.get(&standings[b].name).rating
+                .total_cmp(&ratings.get(&standings[a].name).rating))
+    });
+    let bye = if order.len().is_multiple_of(2) {
+        None
+    } else {
+        let fewest = order.iter().map(|&entrant| standings[entrant].byes).min();
+        let position = order.iter().rposition(|&entrant| Some(standings[entrant].byes) == fewest);
+        position.map(|position| order.remove(position))
+    };
+
+    let mut pairs = Vec::new();
+    while !order.is_empty() {
+        let first = order.remove(0);
+        let fresh = order.iter()
+            .position(|&other| !played.contains(&(first.min(other), first.max(other))));
+        let second = order.remove(fresh.unwrap_or(0));
+        pairs.push((first, second));
+    }
+    (pairs, bye)
+}
+
+// An Elo rating, and the record it was earned over.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    pub rating: f64,
+    pub games: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self { rating: INITIAL_RATING, games: 0, wins: 0, losses: 0, draws: 0 }
+    }
+}
+
+// Every bot's rating, carried over from one tournament to the next by name.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ratings {
+    version: u32,
+    players: BTreeMap<String, Rating>,
+}
+
+impl Default for Ratings {
+    fn default() -> Self {
+        Self { version: RATINGS_VERSION, players: BTreeMap::new() }
+    }
+}
+
+impl Ratings {
+    pub fn default_path() -> PathBuf {
+        storage::data_dir().join("ratings.json")
+    }
+
+    // Starts a fresh table if there isn't one saved yet.
+    pub fn load_or_default(path: &Path) -> io::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let ratings: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if ratings.version != RATINGS_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported ratings version {}", ratings.version),
+            ));
+        }
+        Ok(ratings)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn get(&self, name: &str) -> Rating {
+        self.players.get(name).cloned().unwrap_or_default()
+    }
+
+    // Updates both players' ratings after a game between them. `score` is 1.0
+    // if the first won, 0.0 if the second did and 0.5 for a draw.
+    pub fn record(&mut self, first: &str, second: &str, score: f64) {
+        let (a, b) = (self.get(first), self.get(second));
+        let expected = 1.0 / (1.0 + 10f64.powf((b.rating - a.rating) / 400.0));
+        let change = K_FACTOR * (score - expected);
+        for (name, change, score) in [(first, change, score), (second, -change, 1.0 - score)] {
+            let rating = self.players.entry(name.to_string()).or_default();
+            rating.rating += change;
+            rating.games += 1;
+            if score > 0.5 {
+                rating.wins += 1;
+            } else if score < 0.5 {
+                rating.losses += 1;
+            } else {
+                rating.draws += 1;
+            }
+        }
+    }
+
+    // Everyone rated, highest first.
+    pub fn table(&self) -> Vec<(&str, &Rating)> {
+        let mut table: Vec<_> = self.players.iter()
+            .map(|(name, rating)| (name.as_str(), rating))
+            .collect();
+        table.sort_by(|a, b| b.1.rating.total_cmp(&a.1.rating));
+        table
+    }
+}
+
+pub fn format_standings(standings: &[Standing], ratings: &Ratings) -> String {
+    let mut table = format!(
+        "{:<20}{:>8}{:>9}{:>7}{:>7}{:>8}\n", "", "points", "matches", "won", "lost", "rating",
+    );
+    for standing in standings {
+        table.push_str(&format!(
+            "{:<20}{:>8.1}{:>9}{:>7}{:>7}{:>8.0}\n",
+            standing.name,
+            standing.points,
+            standing.matches,
+            standing.games_won,
+            standing.games_lost,
+            ratings.get(&standing.name).rating,
+        ));
+    }
+    table
+}
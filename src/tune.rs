@@ -0,0 +1,221 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::bot::{Heuristic, WEIGHT_COUNT, Weights};
+use crate::mode::Mode;
+use crate::randomizer::Randomizer;
+use crate::sim;
+
+// Bump this whenever `Tuner` changes shape, so old checkpoints are rejected
+// rather than misread.
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+// Settings for a tuning run. They aren't part of the checkpoint, so they can
+// be changed when a run is resumed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TuneConfig {
+    pub mode: Mode,
+    pub randomizer: Randomizer,
+    // Games each set of weights plays per generation, and how long they can
+    // go on for.
+    pub games: u64,
+    pub max_pieces: u32,
+    // How many of the population go into each tournament when picking a
+    // parent, and how many of the best carry over unchanged.
+    pub tournament_size: usize,
+    pub elite: usize,
+    // The chance of each weight being nudged in a child, and by how much at
+    // most.
+    pub mutation_rate: f64,
+    pub mutation_size: f64,
+    pub threads: usize,
+}
+
+impl Default for TuneConfig {
+    fn default() -> Self {
+        Self {
+            mode: Mode::default(),
+            randomizer: Randomizer::default(),
+            games: 4,
+            max_pieces: 500,
+            tournament_size: 3,
+            elite: 2,
+            mutation_rate: 0.2,
+            mutation_size: 0.5,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+        }
+    }
+}
+
+// A set of weights and the mean score it got.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Scored {
+    pub weights: Weights,
+    pub fitness: f64,
+}
+
+// Evolves bot weights: each generation plays games with every member of the
+// population, then breeds the next one by tournament selection, uniform
+// crossover and mutation. The whole state is saved between generations so a
+// run can be stopped and picked up again.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tuner {
+    version: u32,
+    generation: u32,
+    rng: ChaCha8Rng,
+    population: Vec<Weights>,
+    best: Option<Scored>,
+}
+
+impl Tuner {
+    // Starts from the given weights and mutated copies of them.
+    pub fn new(size: usize, start: &Weights, seed: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut population = vec![start.clone()];
+        while population.len() < size.max(1) {
+            population.push(mutate(start, 1.0, 1.0, &mut rng));
+        }
+        Self {
+            version: CHECKPOINT_VERSION,
+            generation: 0,
+            rng,
+            population,
+            best: None,
+        }
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    // The best weights seen in any generation so far.
+    pub fn best(&self) -> Option<&Scored> {
+        self.best.as_ref()
+    }
+
+    // Plays out one generation and breeds the next, returning how each
+    // member did.
+    pub fn step(&mut self, config: &TuneConfig) -> io::Result<Vec<Scored>> {
+        // Everyone in a generation plays the same seeds, so they're compared
+        // fairly, but the seeds change between generations so the weights
+        // don't just learn a few games.
+        let first_seed = self.generation as u64 * config.games;
+        let fitness = evaluate_all(&self.population, config, first_seed)?;
+        let mut scored: Vec<Scored> = self.population.iter().cloned()
+            .zip(fitness)
+            .map(|(weights, fitness)| Scored { weights, fitness })
+            .collect();
+        scored.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+        if self.best.as_ref().is_none_or(|best| scored[0].fitness > best.fitness) {
+            self.best = Some(scored[0].clone());
+        }
+
+        let mut next: Vec<Weights> = scored.iter()
+            .take(config.elite)
+            .map(|member| member.weights.clone())
+            .collect();
+        while next.len() < scored.len() {
+            let mother = tournament(&scored, config.tournament_size, &mut self.rng);
+            let father = tournament(&scored, config.tournament_size, &mut self.rng);
+            let child = crossover(mother, father, &mut self.rng);
+            next.push(mutate(&child, config.mutation_rate, config.mutation_size, &mut self.rng));
+        }
+        self.population = next;
+        self.generation += 1;
+        Ok(scored)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let tuner: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if tuner.version != CHECKPOINT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported checkpoint version {}", tuner.version),
+            ));
+        }
+        Ok(tuner)
+    }
+}
+
+// The mean score of a set of weights over the given games.
+pub fn fitness(weights: &Weights, config: &TuneConfig, first_seed: u64) -> io::Result<f64> {
+    let mut heuristic = Heuristic::new(weights.clone(), 0);
+    let seeds = first_seed..first_seed + config.games;
+    let results = sim::run(
+        &mut heuristic, &config.mode, config.randomizer, seeds, config.max_pieces,
+    )?;
+    let total: i64 = results.iter().map(|result| result.score as i64).sum();
+    Ok(total as f64 / results.len().max(1) as f64)
+}
+
+// Works out the fitness of every member of the population, spreading them
+// across threads.
+fn evaluate_all(
+    population: &[Weights],
+    config: &TuneConfig,
+    first_seed: u64,
+) -> io::Result<Vec<f64>> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![0.0; population.len()]);
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..config.threads.max(1))
+            .map(|_| scope.spawn(|| -> io::Result<()> {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(weights) = population.get(i) else {
+                        return Ok(());
+                    };
+                    let score = fitness(weights, config, first_seed)?;
+                    results.lock().unwrap()[i] = score;
+                }
+            }))
+            .collect();
+        workers.into_iter()
+            .try_for_each(|worker| worker.join().expect("tuning thread panicked"))
+    })?;
+    Ok(results.into_inner().unwrap())
+}
+
+// The fittest of a few members picked at random.
+fn tournament<'a>(scored: &'a [Scored], size: usize, rng: &mut impl Rng) -> &'a Weights {
+    let winner = (0..size.max(1))
+        .map(|_| &scored[rng.gen_range(0..scored.len())])
+        .max_by(|a, b| a.fitness.total_cmp(&b.fitness))
+        .expect("tournaments have at least one entrant");
+    &winner.weights
+}
+
+// Takes each weight from one parent or the other at random.
+fn crossover(mother: &Weights, father: &Weights, rng: &mut impl Rng) -> Weights {
+    let (mother, father) = (mother.to_array(), father.to_array());
+    let mut child = [0.0; WEIGHT_COUNT];
+    for i in 0..WEIGHT_COUNT {
+        child[i] = if rng.gen_bool(0.5) { mother[i] } else { father[i] };
+    }
+    Weights::from_array(child)
+}
+
+fn mutate(weights: &Weights, rate: f64, size: f64, rng: &mut impl Rng) -> Weights {
+    let mut values = weights.to_array();
+    for value in &mut values {
+        if rng.gen_bool(rate.clamp(0.0, 1.0)) {
+            *value += rng.gen_range(-size..=size);
+        }
+    }
+    Weights::from_array(values)
+}
@@ -0,0 +1,85 @@
+use crate::game::{Command, Game};
+use crate::garbage::GarbageRules;
+use crate::mode::Mode;
+
+pub const PLAYERS: usize = 2;
+
+// Two games played side by side, each player's attacks being sent to the
+// other as garbage. Both games start from the same seed, so they're dealt the
+// same pieces until garbage comes into play.
+#[derive(Debug, PartialEq)]
+pub struct Versus {
+    games: [Game; PLAYERS],
+}
+
+impl Versus {
+    pub fn new(mode: Mode, seed: u64) -> Self {
+        Self::with_rules(mode, seed, GarbageRules::default())
+    }
+
+    pub fn with_rules(mode: Mode, seed: u64, rules: GarbageRules) -> Self {
+        let mut games = [Game::with_seed(mode.clone(), seed), Game::with_seed(mode, seed)];
+        for game in &mut games {
+            game.set_garbage_rules(rules);
+        }
+        Self::from_games(games)
+    }
+
+    pub fn from_games(games: [Game; PLAYERS]) -> Self {
+        Self { games }
+    }
+
+    // Runs one frame of both games, then passes on whatever garbage each sent.
+    pub fn tick(&mut self, commands: [Command; PLAYERS]) {
+        if self.is_over() {
+            return;
+        }
+        for (game, command) in self.games.iter_mut().zip(commands) {
+            game.tick(command);
+        }
+        for player in 0..PLAYERS {
+            let attack = self.games[player].take_attack();
+            self.games[opponent(player)].receive_garbage(attack);
+        }
+    }
+
+    pub fn games(&self) -> &[Game; PLAYERS] {
+        &self.games
+    }
+
+    pub fn game(&self, player: usize) -> &Game {
+        &self.games[player]
+    }
+
+    // For driving one player's game directly, when the two aren't ticked
+    // together.
+    pub fn game_mut(&mut self, player: usize) -> &mut Game {
+        &mut self.games[player]
+    }
+
+    // The match is over as soon as either game is.
+    pub fn is_over(&self) -> bool {
+        self.games.iter().any(Game::is_over)
+    }
+
+    // The player who won, once the match is over. Whoever's game ended first
+    // loses, unless it ended by reaching the mode's goal. None while it's
+    // still going, or if both games ended on the same frame.
+    pub fn winner(&self) -> Option<usize> {
+        let first = (0..PLAYERS)
+            .filter_map(|player| Some((self.games[player].finished_at()?, player)))
+            .min()?;
+        let tied = (0..PLAYERS).any(|player| {
+            player != first.1 && self.games[player].finished_at() == Some(first.0)
+        });
+        match first {
+            _ if tied => None,
+            (_, player) if self.games[player].is_complete() => Some(player),
+            (_, player) => Some(opponent(player)),
+        }
+    }
+}
+
+pub fn opponent(player: usize) -> usize {
+    (player + 1) % PLAYERS
+}
@@ -0,0 +1,58 @@
+// Steps the environment through fast games, where gravity can carry a piece
+// past the placement it was sent to.
+
+use tetris::env::{Action, ActionSpace, Env, ScoreDelta, Shaped};
+use tetris::game::Cell;
+use tetris::mode::{Marathon, Mode};
+use tetris::randomizer::Randomizer;
+
+const STEPS: u64 = 300;
+
+#[test]
+fn steps_report_where_pieces_actually_locked() {
+    let mode = Mode::Marathon(Marathon { start_level: 15, ..Marathon::default() });
+    let mut env = Env::new(mode, Randomizer::Classic, ActionSpace::Placements, ScoreDelta);
+    let mut missed = 0;
+    for seed in 0..10 {
+        env.reset(seed);
+        for step in 0..STEPS {
+            if env.game().is_over() {
+                break;
+            }
+            // Spreads the choices around, so some are far from where the
+            // piece spawns.
+            let index = (step * 7 + seed) as usize % env.placements().len();
+            let wanted = env.placements()[index].piece.clone();
+            let (_, _, done, info) = env.step(Action::Placement(index));
+            if done {
+                break;
+            }
+            let placed = info.placed.expect("the piece should have locked");
+            if placed != wanted {
+                missed += 1;
+            }
+            if info.cleared == 0 {
+                let board = env.game().board();
+                assert!(
+                    placed.get_coords().iter().all(|&(y, x)| board[y as usize][x as usize] != Cell::Empty),
+                    "{placed:?} isn't on the board",
+                );
+            }
+        }
+    }
+    assert!(missed > 0, "gravity never kept a piece from its placement");
+}
+
+// The rewards for an episode's first few placements.
+fn opening_rewards(env: &mut Env<Shaped>, seed: u64) -> Vec<f64> {
+    env.reset(seed);
+    (0..5).map(|_| env.step(Action::Placement(0)).1).collect()
+}
+
+#[test]
+fn shaped_rewards_start_afresh_on_reset() {
+    let new_env = || Env::new(Mode::Marathon(Marathon::default()), Randomizer::Classic, ActionSpace::Placements, Shaped::default());
+    let mut env = new_env();
+    opening_rewards(&mut env, 1);
+    assert_eq!(opening_rewards(&mut env, 2), opening_rewards(&mut new_env(), 2));
+}
@@ -0,0 +1,38 @@
+// Counts finesse faults the way the stats panel shows them, in modes other
+// than the finesse trainer.
+
+use tetris::game::{Command, Game};
+use tetris::mode::{Marathon, Mode};
+
+// Shuffles the piece back and forth before dropping it where it started.
+const WASTED: [Command; 5] = [Command::Left, Command::Right, Command::Left, Command::Right, Command::HardDrop];
+
+#[test]
+fn marathon_counts_finesse_faults() {
+    let mut game = Game::with_seed(Mode::Marathon(Marathon::default()), 1);
+    for command in WASTED {
+        game.tick(command);
+    }
+    assert_eq!(game.render_pieces(), 1);
+    assert_eq!(game.stats().finesse_faults, 4);
+}
+
+#[test]
+fn skipping_finesse_leaves_faults_uncounted() {
+    let mut game = Game::with_seed(Mode::Marathon(Marathon::default()), 1);
+    game.skip_finesse();
+    for command in WASTED {
+        game.tick(command);
+    }
+    assert_eq!(game.stats().finesse_faults, 0);
+}
+
+#[test]
+fn the_trainer_counts_faults_even_when_skipping() {
+    let mut game = Game::with_seed(Mode::Finesse, 1);
+    game.skip_finesse();
+    for command in WASTED {
+        game.tick(command);
+    }
+    assert_eq!(game.stats().finesse_faults, 4);
+}
@@ -0,0 +1,113 @@
+// Checks fumen against fixed data laid out as tetris-fumen, the reference
+// implementation, encodes it. Encoding and decoding mirror each other, so a
+// round trip alone wouldn't notice a piece stored by the wrong cell.
+
+use std::io;
+
+use tetris::fumen::{self, Page};
+use tetris::game::{Board, Cell, HEIGHT, WIDTH};
+use tetris::tbp::{Location, Orientation, Piece};
+
+const EMPTY_BOARD: Board = [[Cell::Empty; WIDTH as usize]; HEIGHT as usize];
+
+fn location(piece: Piece, orientation: Orientation, x: i32, y: i32) -> Location {
+    Location { piece, orientation, x, y }
+}
+
+// An O resting in the bottom left corner.
+const O_ON_FLOOR: &str = "v115@vhATJJ";
+
+// Every piece and orientation fumen stores by a cell other than the one it
+// turns around, each locking in turn.
+const SHIFTED_PIECES: &str = "v115@vhITJJ5oB3qB8rBbbBDYBBIBPRBV0A";
+
+// A row of garbage with a vertical I over the hole that hasn't locked.
+const UNLOCKED_OVER_GARBAGE: &str = "v115@bhD8AeE8JeZBn";
+
+// A flat I stored by the top left cell, leaving its first block off the
+// left edge.
+const OFF_THE_LEFT_EDGE: &str = "v115@vhARAA";
+
+fn shifted_pieces() -> Vec<Location> {
+    vec![
+        location(Piece::O, Orientation::North, 0, 0),
+        location(Piece::I, Orientation::West, 9, 1),
+        location(Piece::S, Orientation::North, 3, 0),
+        location(Piece::Z, Orientation::West, 6, 1),
+        location(Piece::O, Orientation::West, 3, 3),
+        location(Piece::O, Orientation::South, 7, 5),
+        location(Piece::I, Orientation::South, 5, 8),
+        location(Piece::S, Orientation::East, 1, 6),
+        location(Piece::T, Orientation::North, 4, 12),
+    ]
+}
+
+fn garbage_board() -> Board {
+    let mut board = EMPTY_BOARD;
+    board[HEIGHT as usize - 1] = [Cell::Garbage; WIDTH as usize];
+    board[HEIGHT as usize - 1][4] = Cell::Empty;
+    board
+}
+
+#[test]
+fn decodes_a_piece_by_the_cell_it_turns_around() {
+    let pages = fumen::decode(O_ON_FLOOR).unwrap();
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].piece, Some(location(Piece::O, Orientation::North, 0, 0)));
+    assert!(pages[0].lock);
+    assert_eq!(pages[0].board().unwrap(), EMPTY_BOARD);
+}
+
+#[test]
+fn encodes_a_piece_by_the_cell_fumen_stores() {
+    let page = Page::new(&EMPTY_BOARD, Some(location(Piece::O, Orientation::North, 0, 0)), String::new());
+    assert_eq!(fumen::encode(&[page]), O_ON_FLOOR);
+}
+
+#[test]
+fn decodes_every_shifted_piece() {
+    let pages = fumen::decode(SHIFTED_PIECES).unwrap();
+    let pieces: Vec<Option<Location>> = pages.iter().map(|page| page.piece).collect();
+    assert_eq!(pieces, shifted_pieces().into_iter().map(Some).collect::<Vec<_>>());
+
+    // The first pieces lock into the bottom rows of the field the next page
+    // starts from.
+    let board = pages[4].board().unwrap();
+    let bottom = HEIGHT as usize - 1;
+    assert_eq!(board[bottom][..2], [Cell::OBlock; 2]);
+    assert_eq!(board[bottom - 1][..2], [Cell::OBlock; 2]);
+    assert_eq!(board[bottom][2..4], [Cell::SBlock; 2]);
+    assert_eq!(board[bottom - 1][3..5], [Cell::SBlock; 2]);
+    assert_eq!(board[bottom][5], Cell::ZBlock);
+    assert_eq!(board[bottom - 1][5..7], [Cell::ZBlock; 2]);
+    assert_eq!(board[bottom - 2][6], Cell::ZBlock);
+    assert!((0..4).all(|row| board[bottom - row][9] == Cell::IBlock));
+}
+
+#[test]
+fn encodes_every_shifted_piece() {
+    let pages = fumen::decode(SHIFTED_PIECES).unwrap();
+    assert_eq!(fumen::encode(&pages), SHIFTED_PIECES);
+}
+
+#[test]
+fn decodes_an_unlocked_piece_over_garbage() {
+    let pages = fumen::decode(UNLOCKED_OVER_GARBAGE).unwrap();
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].piece, Some(location(Piece::I, Orientation::West, 4, 2)));
+    assert!(!pages[0].lock);
+    assert_eq!(pages[0].board().unwrap(), garbage_board());
+}
+
+#[test]
+fn encodes_an_unlocked_piece_over_garbage() {
+    let mut page = Page::new(&garbage_board(), Some(location(Piece::I, Orientation::West, 4, 2)), String::new());
+    page.lock = false;
+    assert_eq!(fumen::encode(&[page]), UNLOCKED_OVER_GARBAGE);
+}
+
+#[test]
+fn rejects_a_piece_off_the_field() {
+    let err = fumen::decode(OFF_THE_LEFT_EDGE).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
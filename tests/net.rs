@@ -0,0 +1,285 @@
+// Plays networked matches between bots over localhost, with the host and
+// guest each on their own end of a real connection, mostly on threads of
+// this process and once with the guest in a process of its own.
+
+use std::io::{self, Cursor};
+use std::net::{TcpListener, TcpStream};
+use std::process::{self, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tetris::bot::Bot;
+use tetris::game::Command;
+use tetris::garbage::GarbageRules;
+use tetris::mode::{Marathon, Mode};
+use tetris::net::{self, GUEST, HOST, Message, NetMatch, Spectating};
+use tetris::versus::{self, PLAYERS, Versus};
+
+const SEED: u64 = 7;
+const MAX_FRAMES: u64 = 60 * 60 * 5;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+// Waits until the port's being listened on, and connects to it.
+fn connect_to(port: u16) -> TcpStream {
+    let started = Instant::now();
+    loop {
+        match TcpStream::connect(("127.0.0.1", port)) {
+            Ok(stream) => return stream,
+            Err(err) if started.elapsed() < TIMEOUT => {
+                assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused, "{err}");
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err(err) => panic!("couldn't connect: {err}"),
+        }
+    }
+}
+
+// Hosts on the port and joins it, returning the host's end and the guest's.
+// Whatever's given to do first is run once the host is listening, which is
+// found out by connecting and leaving straight away.
+fn connect_after(port: u16, mode: Mode, before_joining: impl FnOnce()) -> (NetMatch, NetMatch) {
+    let host = thread::spawn(move || net::host(port, mode, SEED, GarbageRules::default()));
+    drop(connect_to(port));
+    before_joining();
+    let guest = net::join(&format!("127.0.0.1:{port}")).unwrap();
+    (host.join().unwrap().unwrap(), guest)
+}
+
+fn connect(port: u16) -> (NetMatch, NetMatch) {
+    connect_after(port, Mode::Marathon(Marathon::default()), || ())
+}
+
+// Plays both games with bots until `done` says to stop.
+fn play(host: &mut NetMatch, guest: &mut NetMatch, mut done: impl FnMut(&NetMatch, &NetMatch) -> bool) {
+    let mut bots = [Bot::default(), Bot::default()];
+    let mut frames = 0;
+    while frames < MAX_FRAMES && !host.is_over() && !guest.is_over() && !done(host, guest) {
+        let command = bots[HOST].next_command(host.game()).unwrap();
+        host.tick(command);
+        let command = bots[GUEST].next_command(guest.game()).unwrap();
+        guest.tick(command);
+        host.poll().unwrap();
+        guest.poll().unwrap();
+        frames += 1;
+    }
+}
+
+fn attacks(host: &NetMatch, guest: &NetMatch) -> [u32; PLAYERS] {
+    [host.game(), guest.game()].map(|game| game.stats().attack)
+}
+
+// Polls both ends until each has caught up with the other's game.
+fn settle(host: &mut NetMatch, guest: &mut NetMatch) {
+    let started = Instant::now();
+    while host.versus().game(GUEST).frame() != guest.game().frame()
+        || guest.versus().game(HOST).frame() != host.game().frame()
+    {
+        assert!(started.elapsed() < TIMEOUT, "the replicas never caught up");
+        host.poll().unwrap();
+        guest.poll().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn replicas_stay_in_step_with_the_real_games() {
+    let (mut host, mut guest) = connect(free_port());
+    assert_eq!((host.player(), guest.player()), (HOST, GUEST));
+
+    // Plays on until both sides have sent garbage, so the copies have taken
+    // it in as well.
+    play(&mut host, &mut guest, |host, guest| !attacks(host, guest).contains(&0));
+    settle(&mut host, &mut guest);
+
+    assert!(!attacks(&host, &guest).contains(&0), "both sides should have attacked");
+    assert_eq!(host.versus().game(GUEST).state_hash(), guest.game().state_hash());
+    assert_eq!(guest.versus().game(HOST).state_hash(), host.game().state_hash());
+    assert_eq!(host.desync(), None);
+    assert_eq!(guest.desync(), None);
+}
+
+// Where the guest started by `host_and_guest_in_separate_processes` joins.
+const GUEST_ADDRESS_VAR: &str = "TETRIS_NET_GUEST_ADDRESS";
+const PROCESS_FRAMES: u64 = 300;
+
+// Plays a fixed number of frames with a bot on one end, then waits for the
+// other end's frames to arrive and checks its copy of them.
+fn play_alone(net: &mut NetMatch) {
+    let mut bot = Bot::default();
+    while net.game().frame() < PROCESS_FRAMES && !net.is_over() {
+        let command = bot.next_command(net.game()).unwrap();
+        net.tick(command);
+        net.poll().unwrap();
+    }
+    let opponent = versus::opponent(net.player());
+    let started = Instant::now();
+    while net.versus().game(opponent).frame() < PROCESS_FRAMES && !net.is_disconnected() {
+        assert!(started.elapsed() < TIMEOUT, "the other process's frames never arrived");
+        net.poll().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(net.versus().game(opponent).frame(), PROCESS_FRAMES);
+    assert_eq!(net.desync(), None);
+}
+
+// The guest half of `host_and_guest_in_separate_processes`, run by it in a
+// process of its own with the host's address.
+#[test]
+#[ignore = "run by host_and_guest_in_separate_processes"]
+fn guest_process() {
+    let address = std::env::var(GUEST_ADDRESS_VAR).expect("the host's address should be given");
+    let mut guest = net::join(&address).unwrap();
+    assert_eq!(guest.player(), GUEST);
+    play_alone(&mut guest);
+}
+
+#[test]
+fn host_and_guest_in_separate_processes() {
+    let port = free_port();
+    let host = thread::spawn(move || net::host(port, Mode::Marathon(Marathon::default()), SEED, GarbageRules::default()));
+    drop(connect_to(port));
+    let guest = process::Command::new(std::env::current_exe().unwrap())
+        .args(["guest_process", "--exact", "--ignored", "--quiet"])
+        .env(GUEST_ADDRESS_VAR, format!("127.0.0.1:{port}"))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut host = host.join().unwrap().unwrap();
+    play_alone(&mut host);
+
+    let output = guest.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn hosting_carries_on_past_connections_that_misbehave() {
+    let port = free_port();
+    let (host, guest) = connect_after(port, Mode::Marathon(Marathon::default()), || {
+        // One that says something other than hello or watch.
+        let mut stream = connect_to(port);
+        net::read_message(&mut stream).unwrap();
+        net::write_message(&mut stream, &Message::Bye).unwrap();
+        assert_eq!(net::read_message(&mut stream).unwrap(), None);
+        // And one that gets the hello and leaves.
+        let mut stream = connect_to(port);
+        net::read_message(&mut stream).unwrap();
+    });
+    assert_eq!((host.player(), guest.player()), (HOST, GUEST));
+}
+
+#[test]
+fn late_spectators_catch_up() {
+    let port = free_port();
+    let (mut host, mut guest) = connect(port);
+    play(&mut host, &mut guest, |host, guest| !attacks(host, guest).contains(&0));
+
+    let watching = thread::spawn(move || net::watch(&format!("127.0.0.1:{port}")));
+    let started = Instant::now();
+    while !watching.is_finished() {
+        assert!(started.elapsed() < TIMEOUT, "the spectator was never let in");
+        host.poll().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    let mut spectating: Spectating = watching.join().unwrap().unwrap();
+    settle(&mut host, &mut guest);
+
+    let games = [host.game(), guest.game()];
+    let started = Instant::now();
+    while (0..PLAYERS).any(|player| spectating.versus().game(player).frame() != games[player].frame()) {
+        assert!(started.elapsed() < TIMEOUT, "the spectator never caught up");
+        spectating.poll().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    for (player, game) in games.iter().enumerate() {
+        assert_eq!(spectating.versus().game(player).state_hash(), game.state_hash());
+    }
+    assert_eq!(spectating.status(), "");
+}
+
+#[test]
+fn a_leaving_player_is_noticed() {
+    let (mut host, guest) = connect(free_port());
+    host.tick(Command::NoOp);
+    drop(guest);
+    let started = Instant::now();
+    while !host.is_disconnected() {
+        assert!(started.elapsed() < TIMEOUT, "the guest leaving went unnoticed");
+        host.poll().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert!(host.is_over());
+}
+
+#[test]
+fn a_wrong_state_hash_is_reported_as_a_desync() {
+    let port = free_port();
+    let host = thread::spawn(move || net::host(port, Mode::Marathon(Marathon::default()), SEED, GarbageRules::default()));
+    drop(connect_to(port));
+    // Plays the guest by hand, over the raw connection.
+    let mut stream = connect_to(port);
+    let hello = net::read_message(&mut stream).unwrap().unwrap();
+    net::write_message(&mut stream, &hello).unwrap();
+    let mut host = host.join().unwrap().unwrap();
+
+    // A copy of the guest's game to take the hashes from. The first one sent
+    // is right, and the second is wrong.
+    let mut versus = Versus::with_rules(Mode::Marathon(Marathon::default()), SEED, GarbageRules::default());
+    for frame in 1..=120 {
+        versus.game_mut(GUEST).tick(Command::NoOp);
+        net::write_message(&mut stream, &Message::Input { frame, command: Command::NoOp, garbage: 0 }).unwrap();
+        let hash = versus.game(GUEST).state_hash();
+        match frame {
+            60 => net::write_message(&mut stream, &Message::State { frame, hash }).unwrap(),
+            120 => net::write_message(&mut stream, &Message::State { frame, hash: !hash }).unwrap(),
+            _ => (),
+        }
+    }
+
+    let started = Instant::now();
+    while host.versus().game(GUEST).frame() < 120 || host.desync().is_none() {
+        assert!(started.elapsed() < TIMEOUT, "the wrong hash went unnoticed");
+        host.poll().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(host.desync(), Some(120));
+    assert_eq!(host.status(), "Out of sync since frame 120!");
+}
+
+#[test]
+fn messages_are_framed_by_length() {
+    let messages = [
+        Message::Input { frame: 1, command: Command::Left, garbage: 2 },
+        Message::Attack { frame: 1, lines: 4 },
+        Message::State { frame: 60, hash: u64::MAX },
+        Message::Bye,
+    ];
+    let mut buffer = Vec::new();
+    for message in &messages {
+        net::write_message(&mut buffer, message).unwrap();
+    }
+    let json_len = u32::from_be_bytes(buffer[..4].try_into().unwrap()) as usize;
+    assert_eq!(buffer[4..4 + json_len], serde_json::to_vec(&messages[0]).unwrap());
+
+    let mut reader = Cursor::new(buffer);
+    for message in &messages {
+        assert_eq!(net::read_message(&mut reader).unwrap().as_ref(), Some(message));
+    }
+    assert_eq!(net::read_message(&mut reader).unwrap(), None);
+}
+
+#[test]
+fn oversized_and_cut_off_messages_are_rejected() {
+    let mut reader = Cursor::new(u32::MAX.to_be_bytes().to_vec());
+    assert_eq!(net::read_message(&mut reader).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    let mut buffer = Vec::new();
+    net::write_message(&mut buffer, &Message::Bye).unwrap();
+    buffer.pop();
+    let mut reader = Cursor::new(buffer);
+    assert_eq!(net::read_message(&mut reader).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,114 @@
+// Runs the Python bindings inside an embedded interpreter, so they can be
+// checked with `cargo test --features python` without installing anything.
+// Checks that need numpy are skipped when it isn't installed.
+#![cfg(feature = "python")]
+
+use std::ffi::CString;
+use std::sync::Once;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use tetris::python::tetris;
+
+fn attach<T>(f: impl FnOnce(Python<'_>) -> T) -> T {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        pyo3::append_to_inittab!(tetris);
+        Python::initialize();
+    });
+    Python::attach(f)
+}
+
+// Runs some Python with the module imported, failing the test on any error.
+fn run(py: Python<'_>, code: &str) {
+    let globals = PyDict::new(py);
+    globals.set_item("__name__", "__main__").unwrap();
+    globals.set_item("tetris", py.import("tetris").unwrap()).unwrap();
+    let code = CString::new(code).unwrap();
+    if let Err(error) = py.run(&code, Some(&globals), None) {
+        error.print(py);
+        panic!("Python code failed");
+    }
+}
+
+fn has_numpy(py: Python<'_>) -> bool {
+    let found = py.import("numpy").is_ok();
+    if !found {
+        eprintln!("numpy isn't installed, skipping");
+    }
+    found
+}
+
+#[test]
+fn plays_placements_until_over() {
+    attach(|py| run(py, r#"
+game = tetris.Game(seed=3, randomizer="bag")
+while not game.is_over and game.pieces < 50:
+    placements = game.placements()
+    assert placements
+    game.place(placements[0])
+assert game.pieces > 0
+if game.is_over:
+    assert game.placements() == []
+"#));
+}
+
+#[test]
+fn ticks_commands_by_name() {
+    attach(|py| run(py, r#"
+game = tetris.Game(mode="zen")
+start = game.active_piece.cells
+game.tick("left")
+assert [x for _, x in game.active_piece.cells] == [x - 1 for _, x in start]
+game.tick("hard_drop")
+assert game.pieces == 1
+for command in tetris.COMMANDS:
+    game.tick(command)
+try:
+    game.tick("jump")
+except ValueError:
+    pass
+else:
+    raise AssertionError("unknown commands should be rejected")
+"#));
+}
+
+#[test]
+fn rejects_unknown_modes() {
+    attach(|py| run(py, r#"
+for kwargs in [{"mode": "sprint"}, {"randomizer": "tgm"}]:
+    try:
+        tetris.Game(**kwargs)
+    except ValueError:
+        pass
+    else:
+        raise AssertionError(f"{kwargs} should be rejected")
+"#));
+}
+
+#[test]
+fn boards_are_numpy_arrays() {
+    attach(|py| {
+        if has_numpy(py) {
+            run(py, r#"
+import numpy as np
+game = tetris.Game()
+board = game.render_board()
+assert board.shape == (tetris.HEIGHT, tetris.WIDTH)
+assert board.dtype == np.uint8
+assert np.count_nonzero(board) > 0
+assert np.count_nonzero(game.board()) == 0
+"#);
+        }
+    });
+}
+
+#[test]
+fn smoke_script() {
+    attach(|py| {
+        if has_numpy(py) {
+            run(py, include_str!("../python/smoke.py"));
+        }
+    });
+}